@@ -0,0 +1,66 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::cell::RefCell;
+use std::convert::TryFrom;
+
+use julia::api::{Julia, Value, JlValue, Datatype, Function, RustBox};
+
+/// A Rust type Julia code gets to use through generic functions.
+struct Stack {
+    items: RefCell<Vec<i64>>,
+}
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+    let main = jl.main().clone();
+    let base = jl.base().clone();
+
+    let stack_type = RustBox::<Stack>::datatype().unwrap();
+    let io_type = Datatype::from_value(base.global("IO").unwrap()).unwrap();
+
+    base.function("length")
+        .unwrap()
+        .add_method(&[stack_type.clone()], |args| {
+            let stack = RustBox::<Stack>::downcast(&args[0])?;
+            let len = stack.get()?.items.borrow().len();
+            Ok(Value::from(len as i64))
+        })
+        .unwrap();
+
+    let print = base.function("print").unwrap();
+    base.function("show")
+        .unwrap()
+        .add_method(&[io_type, stack_type.clone()], move |args| {
+            let stack = RustBox::<Stack>::downcast(&args[1])?;
+            let items = format!("Stack({:?})", stack.get()?.items.borrow());
+            print.call(&[args[0].clone(), Value::from(items)])
+        })
+        .unwrap();
+
+    // methods can be added to our own generic functions as well
+    let result = jl.eval_string("process(x) = \"nothing to do\"").unwrap();
+    let process = Function::from_value(result).unwrap();
+    let method = process
+        .add_method(&[stack_type, Datatype::int64()], |args| {
+            let stack = RustBox::<Stack>::downcast(&args[0])?;
+            stack.get()?.items.borrow_mut().push(i64::try_from(&args[1])?);
+            Ok(Value::nothing())
+        })
+        .unwrap();
+    println!("added {}", method.sig().unwrap());
+
+    let stack = RustBox::new(Stack { items: RefCell::new(vec![]) }).unwrap();
+    main.set("stack", stack.value()).unwrap();
+
+    let ret = jl.eval_string("process(stack, 1); process(stack, 2); length(stack)").unwrap();
+    assert_eq!(i64::try_from(&ret).unwrap(), 2);
+
+    let ret = jl.eval_string("string(stack)").unwrap();
+    assert_eq!(String::try_from(&ret).unwrap(), "Stack([1, 2])");
+
+    // other arguments still go to the other methods
+    let ret = jl.eval_string("process(1)").unwrap();
+    assert_eq!(String::try_from(&ret).unwrap(), "nothing to do");
+}
@@ -4,12 +4,29 @@ extern crate julia;
 
 use julia::api::Julia;
 use julia::api::primitive::*;
+use julia::error::Result;
 
 extern_jl! {
     extern "Julia" libsquare :: LibSquare {
         pub fn square(x: Float64) -> Float64 {
             x * x
         }
+
+        pub fn checked_sqrt(x: Float64) -> Result<Float64> {
+            if x < 0.0 {
+                // turned into an ErrorException instead of unwinding into Julia
+                panic!("checked_sqrt of a negative number");
+            }
+            Ok(x.sqrt())
+        }
+
+        pub fn total(xs: &[Float64]) -> Float64 {
+            xs.iter().sum::<f64>()
+        }
+
+        pub fn greet(name: &str) -> String {
+            format!("Hello, {}!", name)
+        }
     }
 }
 
@@ -33,6 +50,16 @@ libsquare = Libdl.dlopen(dlname)
 ccall((:square_decl, :libsquare), Void, ())
 
 println("square(4.0) = ", square(4.0))
+println("checked_sqrt(4.0) = ", checked_sqrt(4.0))
+
+println("total([1.0, 2.0, 3.0]) = ", total([1.0, 2.0, 3.0]))
+println(greet("Julia"))
+
+try
+    checked_sqrt(-4.0)
+catch ex
+    println(ex)
+end
 
  */
 
@@ -0,0 +1,41 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::cell::Cell;
+
+use julia::api::{Julia, Value};
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+    let main = jl.main().clone();
+
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    jl.register_fn(&main, "rust_add", move |args| {
+        counter.set(counter.get() + 1);
+        let mut sum = 0;
+        for arg in args {
+            sum += i64::try_from(arg)?;
+        }
+        Ok(Value::from(sum))
+    }).unwrap();
+
+    let ret = jl.eval_string("rust_add(1, 2, 3) + rust_add(4)").unwrap();
+    assert_eq!(i64::try_from(&ret).unwrap(), 10);
+    assert_eq!(calls.get(), 2);
+
+    // errors are rethrown as Julia exceptions
+    let ret = jl.eval_string("try rust_add(\"one\") catch ex; ex end").unwrap();
+    println!("{}", ret);
+
+    jl.register_fn(&main, "rust_panic", |_| panic!("oh no")).unwrap();
+    let ret = jl.eval_string("try rust_panic() catch ex; ex.msg end").unwrap();
+    println!("{}", ret);
+    // oh no
+
+    assert!(jl.unregister_fn(&main, "rust_add").unwrap());
+    assert!(jl.eval_string("rust_add(1)").is_err());
+}
@@ -0,0 +1,50 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+use julia::api::{Julia, Value};
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    let mut ages = HashMap::new();
+    ages.insert("alice".to_string(), 31i64);
+    ages.insert("bob".to_string(), 27);
+
+    jl.main().set("ages", &Value::from(ages.clone())).unwrap();
+    jl.eval_string("@assert ages isa Dict{String,Int64}").unwrap();
+    jl.eval_string("@assert ages[\"bob\"] == 27").unwrap();
+
+    let value = jl.eval_string("ages").unwrap();
+    assert_eq!(HashMap::<String, i64>::try_from(&value).unwrap(), ages);
+
+    // element types are inferred from the Rust types
+    let nested = vec![vec![1.0, 2.0], vec![3.0]];
+    jl.main().set("nested", &Value::from(nested)).unwrap();
+    jl.eval_string("@assert nested isa Vector{Vector{Float64}}").unwrap();
+
+    let value = jl.eval_string("Dict(1 => [\"a\"], 2 => String[])").unwrap();
+    let map = BTreeMap::<i64, Vec<String>>::try_from(&value).unwrap();
+    assert_eq!(map[&1], vec!["a".to_string()]);
+    assert!(map[&2].is_empty());
+
+    let tuple = (1u8, 'x', "tuple".to_string(), Some(2.5));
+    jl.main().set("tuple", &Value::from(tuple.clone())).unwrap();
+    jl.eval_string("@assert tuple == (0x01, 'x', \"tuple\", 2.5)").unwrap();
+    let value = jl.eval_string("tuple").unwrap();
+    assert_eq!(
+        <(u8, char, String, Option<f64>)>::try_from(&value).unwrap(),
+        tuple
+    );
+
+    let value = jl.eval_string("nothing").unwrap();
+    assert_eq!(Option::<i64>::try_from(&value).unwrap(), None);
+
+    // Options become unions, and so do the element types of their vectors
+    let options = vec![Some(1i64), None];
+    jl.main().set("options", &Value::from(options)).unwrap();
+    jl.eval_string("@assert options isa Vector{Union{Void,Int64}}").unwrap();
+}
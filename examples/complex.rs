@@ -1,11 +1,10 @@
-
 #![feature(try_from)]
 
 extern crate julia;
 
 use std::convert::TryFrom;
 
-use julia::api::{Julia, Value, JlValue, Function};
+use julia::api::{Julia, Value, JlValue, Function, Complex, Rational};
 
 fn main() {
     let mut jl = Julia::new().unwrap();
@@ -14,9 +13,30 @@ fn main() {
     let f = Function::from_value(result).unwrap();
 
     let x = Value::from(3.0);
-    let y = f.call1(&x).unwrap();
+    let y = f.call(&[x.clone()]).unwrap();
     let y = f64::try_from(&y).unwrap();
 
     assert!((y - (3.0 * 2.0 - 1.0)).abs() < std::f64::EPSILON);
     println!("f({}) = {}", 3.0, y);
+
+    // the same function applied to a Complex{Float64}
+    let z = Complex::new(1.0, 2.0);
+    let w = f.call(&[Value::try_from(z).unwrap()]).unwrap();
+    let w = Complex::<f64>::try_from(&w).unwrap();
+
+    assert_eq!(w, z * Complex::new(2.0, 0.0) - Complex::new(1.0, 0.0));
+    println!("f({}) = {}", z, w);
+
+    // and to a Rational{Int64}
+    let q = Rational::new(3i64, 4);
+    let r = f.call(&[Value::try_from(q).unwrap()]).unwrap();
+    let r = Rational::<i64>::try_from(&r).unwrap();
+
+    assert_eq!(r, Rational::new(1, 2));
+    println!("f({}) = {}", q, r);
+
+    // Rational arithmetic reduces before multiplying, and reports overflow
+    let big = Rational::new(i64::max_value(), 2);
+    assert_eq!(big * Rational::new(2, 3), Rational::new(i64::max_value(), 3));
+    assert_eq!(big.checked_add(big), None);
 }
@@ -0,0 +1,101 @@
+extern crate julia;
+#[macro_use]
+extern crate julia_derive;
+
+use julia::api::{Julia, IntoJulia, FromJulia, JuliaType};
+use julia::error::Error;
+
+#[derive(Debug, PartialEq, JuliaType, IntoJulia, FromJulia)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(Debug, PartialEq, IntoJulia, FromJulia)]
+#[julia(name = "Geometry.Segment")]
+struct Line {
+    #[julia(name = "from")]
+    start: Point,
+    #[julia(name = "to")]
+    end: Point,
+}
+
+#[derive(Debug, PartialEq, JuliaType, IntoJulia, FromJulia)]
+#[julia(mutable)]
+struct Particle {
+    position: Point,
+    mass: f64,
+}
+
+#[derive(Debug, PartialEq, IntoJulia, FromJulia)]
+enum Shape {
+    Empty,
+    Circle { center: Point, radius: f64 },
+    Polygon(i64),
+}
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    jl.eval_string("struct Point; x::Float64; y::Float64; end").unwrap();
+    jl.eval_string("module Geometry
+        import Main.Point
+        struct Segment; from::Point; to::Point; end
+        end")
+        .unwrap();
+    jl.eval_string("struct Circle; center::Point; radius::Float64; end").unwrap();
+    jl.eval_string("struct Polygon; n::Int64; end").unwrap();
+
+    let line = Line {
+        start: Point { x: 0.0, y: 1.0 },
+        end: Point { x: 2.0, y: 3.0 },
+    };
+    let value = line.into_julia().unwrap();
+    jl.main().set("line", &value).unwrap();
+    jl.eval_string("@assert line.to.y == 3.0").unwrap();
+
+    let line = Line::from_julia(&value).unwrap();
+    assert_eq!(line.end, Point { x: 2.0, y: 3.0 });
+
+    let circle = jl.eval_string("Circle(Point(1.0, 1.0), 0.5)").unwrap();
+    let circle = Shape::from_julia(&circle).unwrap();
+    assert_eq!(
+        circle,
+        Shape::Circle {
+            center: Point { x: 1.0, y: 1.0 },
+            radius: 0.5,
+        }
+    );
+
+    let empty = jl.eval_string(":Empty").unwrap();
+    assert_eq!(Shape::from_julia(&empty).unwrap(), Shape::Empty);
+    let polygon = Shape::Polygon(6).into_julia().unwrap();
+    assert_eq!(Shape::from_julia(&polygon).unwrap(), Shape::Polygon(6));
+
+    // Particle is defined from the Rust struct, Point already exists
+    Particle::julia_type(&jl).unwrap();
+    jl.eval_string("p = Particle(Point(0.0, 0.0), 1.0); p.mass = 2.0").unwrap();
+    let particle = jl.eval_string("p").unwrap();
+    let particle = Particle::from_julia(&particle).unwrap();
+    assert_eq!(particle.mass, 2.0);
+
+    // a Point isn't a Line
+    let point = jl.eval_string("Point(0.0, 0.0)").unwrap();
+    match Line::from_julia(&point) {
+        Err(Error::TypeMismatch) => {}
+        other => panic!("expected a type mismatch, got {:?}", other),
+    }
+
+    // the fields of the Julia type are checked
+    jl.eval_string("struct Point3; x::Float64; y::Float64; z::Float64; end").unwrap();
+    #[derive(Debug, IntoJulia)]
+    struct Point3 {
+        x: f64,
+        y: f64,
+    }
+    match (Point3 { x: 0.0, y: 0.0 }).into_julia() {
+        Err(Error::FieldMismatch(ref field)) if field == "z" => {}
+        Err(err) => panic!("expected a field mismatch, got {:?}", err),
+        Ok(_) => panic!("expected a field mismatch"),
+    }
+}
@@ -0,0 +1,50 @@
+extern crate julia;
+
+use julia::api::{Julia, JuliaValue};
+use julia::error::Error;
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    jl.eval_string("struct Point; x::Float64; y::Float64; end").unwrap();
+    let value = jl.eval_string("(1, :a, [Point(0.0, 1.0)], Dict(\"n\" => nothing))")
+        .unwrap();
+
+    let tree = JuliaValue::new(&value).unwrap();
+    println!("{:?}", tree);
+    assert_eq!(
+        tree,
+        JuliaValue::Tuple(vec![
+            JuliaValue::Int(1),
+            JuliaValue::Symbol("a".to_string()),
+            JuliaValue::Array(vec![
+                JuliaValue::Struct {
+                    type_name: "Point".to_string(),
+                    fields: vec![
+                        ("x".to_string(), JuliaValue::Float(0.0)),
+                        ("y".to_string(), JuliaValue::Float(1.0)),
+                    ],
+                },
+            ]),
+            JuliaValue::Dict(vec![
+                (JuliaValue::String("n".to_string()), JuliaValue::Nothing),
+            ]),
+        ])
+    );
+
+    // and back again
+    let value = tree.to_value().unwrap();
+    jl.main().set("value", &value).unwrap();
+    jl.eval_string("@assert value[3] == [Point(0.0, 1.0)]").unwrap();
+    jl.eval_string("@assert value[4] == Dict(\"n\" => nothing)").unwrap();
+
+    // cyclic values hit the depth limit
+    jl.eval_string("mutable struct Node; next::Any; Node() = (n = new(); n.next = n); end")
+        .unwrap();
+    let node = jl.eval_string("Node()").unwrap();
+    match JuliaValue::with_max_depth(&node, 16) {
+        Err(Error::DepthLimit) => {}
+        Err(err) => panic!("expected the depth limit, got {:?}", err),
+        Ok(_) => panic!("expected the depth limit"),
+    }
+}
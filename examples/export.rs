@@ -0,0 +1,65 @@
+#![feature(proc_macro)]
+
+extern crate julia;
+extern crate julia_derive;
+
+use std::cell::Cell;
+use std::ops::Add;
+
+use julia::api::Julia;
+use julia_derive::julia_export;
+
+/// Adds two numbers.
+#[julia_export(i64, f64)]
+pub fn add<T: Add<Output = T>>(a: T, b: T) -> T {
+    a + b
+}
+
+/// Splits a number into its integer and fractional part.
+#[julia_export]
+pub fn int_frac(x: f64) -> (i64, f64) {
+    (x.trunc() as i64, x.fract())
+}
+
+/// Counts things.
+pub struct Counter {
+    count: Cell<i64>,
+}
+
+#[julia_export]
+impl Counter {
+    /// Creates a new counter starting at `start`.
+    pub fn new(start: i64) -> Counter {
+        Counter { count: Cell::new(start) }
+    }
+
+    /// Increments the counter and returns the new count.
+    pub fn incr(&self) -> i64 {
+        self.count.set(self.count.get() + 1);
+        self.count.get()
+    }
+
+    pub fn current(&self) -> i64 {
+        self.count.get()
+    }
+}
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+    let main = jl.main().clone();
+
+    register_add(&main).unwrap();
+    register_int_frac(&main).unwrap();
+    Counter::register_julia(&main).unwrap();
+
+    jl.eval_string("@assert add(1, 2) == 3").unwrap();
+    jl.eval_string("@assert add(1.5, 2.0) == 3.5").unwrap();
+    jl.eval_string("@assert int_frac(2.5) == (2, 0.5)").unwrap();
+
+    jl.eval_string("c = Counter(41)").unwrap();
+    jl.eval_string("@assert incr(c) == 42").unwrap();
+    jl.eval_string("@assert current(c) == 42").unwrap();
+
+    // the counter can only be used with its own methods
+    assert!(jl.eval_string("incr(1)").is_err());
+}
@@ -0,0 +1,36 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::convert::TryFrom;
+
+use julia::api::{Julia, Datatype};
+use julia::api::iter::{from_rust_iter, from_exact_rust_iter};
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    let squares = jl.eval_string("(x^2 for x in 1:5)").unwrap();
+    let squares: Vec<i64> = squares
+        .iter()
+        .unwrap()
+        .map(|x| i64::try_from(&x.unwrap()).unwrap())
+        .collect();
+    assert_eq!(squares, vec![1, 4, 9, 16, 25]);
+
+    let dict = jl.eval_string("Dict(:a => 1, :b => 2)").unwrap();
+    for pair in dict.iter().unwrap() {
+        println!("{}", pair.unwrap());
+    }
+
+    // Rust iterators can be consumed lazily by Julia code.
+    let sum = jl.base().function("sum").unwrap();
+    let evens = from_rust_iter((1..).map(|x: i64| x * 2).take(10), None).unwrap();
+    let total = i64::try_from(&sum.call(&[evens.clone()]).unwrap()).unwrap();
+    assert_eq!(total, 110);
+
+    let collect = jl.base().function("collect").unwrap();
+    let floats = vec![1.0, 4.0, 9.0].into_iter();
+    let floats = from_exact_rust_iter(floats, Some(&Datatype::float64())).unwrap();
+    println!("{}", collect.call(&[floats.clone()]).unwrap());
+}
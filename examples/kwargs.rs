@@ -0,0 +1,35 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::convert::TryFrom;
+
+use julia::api::{Julia, Value, JlValue, Function, Exception};
+use julia::error::Error;
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    let result = jl.eval_string("clamp_scaled(x; lo = 0.0, hi = 1.0, scale = 1.0) =
+        clamp(x * scale, lo, hi)")
+        .unwrap();
+    let f = Function::from_value(result).unwrap();
+
+    let x = Value::from(3.0);
+    let hi = Value::from(10.0);
+    let scale = Value::from(2.0);
+
+    let y = f.call_kw(&[x.clone()], &[("hi", &hi), ("scale", &scale)]).unwrap();
+    assert_eq!(f64::try_from(&y).unwrap(), 6.0);
+
+    // without keywords the defaults are used
+    let y = f.call_kw(&[x.clone()], &[] as &[(&str, &Value)]).unwrap();
+    assert_eq!(f64::try_from(&y).unwrap(), 1.0);
+
+    // unknown keywords are reported as MethodErrors
+    match f.call_kw(&[x], &[("step", &scale)]) {
+        Err(Error::UnhandledException(Exception::Method(_))) => {}
+        Err(err) => panic!("expected a MethodError, got {:?}", err),
+        Ok(_) => panic!("expected a MethodError"),
+    }
+}
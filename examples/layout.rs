@@ -0,0 +1,70 @@
+#[macro_use]
+extern crate julia;
+
+use julia::api::{Julia, Datatype, IsBits, JlValue, Module};
+use julia::api::datatype::LayoutMismatch;
+use julia::error::Result;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Vec3 {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+unsafe impl IsBits for Vec3 {
+    fn datatype() -> Result<Datatype> {
+        let main = unsafe { Module::new_unchecked(julia::sys::jl_main_module) };
+        Datatype::from_value(main.global("Vec3")?)
+    }
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct Pixel {
+    value: f32,
+    alpha: u8,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct Sample {
+    t: f64,
+    v: i64,
+}
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    jl.eval_string("struct Vec3; x::Float64; y::Float64; z::Float64; end").unwrap();
+    jl.eval_string("struct Pixel; value::Float64; alpha::UInt8; end").unwrap();
+
+    let vec3 = Vec3::datatype().unwrap();
+    let mismatches = vec3.check_layout(&jl_layout!(Vec3 { x, y, z })).unwrap();
+    assert!(mismatches.is_empty());
+
+    // with a matching layout, values can be read in place
+    let value = jl.eval_string("Vec3(1.0, 2.0, 3.0)").unwrap();
+    let v: &Vec3 = value.bits_ref().unwrap();
+    assert_eq!(*v, Vec3 { x: 1.0, y: 2.0, z: 3.0 });
+
+    // the Julia Pixel uses a Float64
+    let pixel = Datatype::from_value(jl.main().global("Pixel").unwrap()).unwrap();
+    let mismatches = pixel.check_layout(&jl_layout!(Pixel { value, alpha })).unwrap();
+    for mismatch in &mismatches {
+        println!("{:?}", mismatch);
+    }
+    assert!(mismatches.contains(&LayoutMismatch::Size { julia: 16, rust: 8 }));
+    assert!(mismatches.contains(&LayoutMismatch::FieldType {
+        index: 0,
+        julia: "Float64".to_string(),
+        rust: "Float32".to_string(),
+    }));
+
+    // same offsets and sizes, but the field types are swapped
+    jl.eval_string("struct Sample; t::Int64; v::Float64; end").unwrap();
+    let sample = Datatype::from_value(jl.main().global("Sample").unwrap()).unwrap();
+    let mismatches = sample.check_layout(&jl_layout!(Sample { t, v })).unwrap();
+    assert_eq!(mismatches.len(), 2);
+}
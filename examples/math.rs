@@ -1,22 +1,25 @@
-
-#![feature(try_from)]
-
 extern crate julia;
 
-use std::convert::TryFrom;
-
-use julia::api::{Julia, Value};
+use julia::api::Julia;
+use julia::error::Error;
 
 fn main() {
     let jl = Julia::new().unwrap();
 
     let sqrt = jl.base().function("sqrt").unwrap();
     let x = 3.0;
-    let y = {
-        let x = Value::from(x);
-        sqrt.call1(&x).unwrap()
-    };
-    let y = f64::try_from(&y).unwrap();
+    let y: f64 = sqrt.invoke((x,)).unwrap();
 
     println!("sqrt({}) = {}", x, y);
+
+    let hypot = jl.base().function("hypot").unwrap();
+    let z: f64 = hypot.invoke((3.0, 4i64)).unwrap();
+    assert_eq!(z, 5.0);
+
+    // the result of sqrt(::Float64) can't be read as an integer
+    match sqrt.invoke::<i64, _>((x,)) {
+        Err(err @ Error::ReturnMismatch(..)) => println!("{}", err),
+        Err(err) => panic!("expected a return mismatch, got {:?}", err),
+        Ok(_) => panic!("expected a return mismatch"),
+    }
 }
@@ -0,0 +1,37 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::convert::TryFrom;
+
+use julia::api::{Julia, Value, JlValue, Function, Datatype, Svec};
+use julia::api::datatype::Tuple;
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    jl.eval_string("describe(x) = \"anything\"").unwrap();
+    jl.eval_string("describe(x::Number) = \"a number\"").unwrap();
+    let result = jl.eval_string("describe(x::Int64) = \"an integer\"").unwrap();
+    let describe = Function::from_value(result).unwrap();
+
+    let sigs = describe.methods().unwrap();
+    for sig in &sigs {
+        println!("{}", sig);
+    }
+    assert_eq!(sigs.len(), 3);
+
+    // the method picked for an Int64
+    let int64 = Datatype::int64().to_value().unwrap();
+    let arg_types = Tuple::apply(&Svec::from_values(vec![int64]).unwrap()).unwrap();
+    let method = describe.which(&arg_types).unwrap();
+    println!("{} at {}:{}", method, method.file().unwrap(), method.line().unwrap());
+    assert_eq!(method.name().unwrap(), "describe");
+
+    // call the less specific Number method for an Int64
+    let number = Datatype::number().to_value().unwrap();
+    let arg_types = Tuple::apply(&Svec::from_values(vec![number]).unwrap()).unwrap();
+    let x = Value::from(1i64);
+    let result = describe.invoke_method(&arg_types, &[x]).unwrap();
+    assert_eq!(String::try_from(&result).unwrap(), "a number");
+}
@@ -0,0 +1,55 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use julia::api::{Julia, Value, RustBox};
+
+/// Some Rust state Julia code can hold on to, but not look into.
+struct Cache {
+    entries: RefCell<HashMap<String, i64>>,
+}
+
+impl Drop for Cache {
+    fn drop(&mut self) {
+        println!("dropping cache with {} entries", self.entries.borrow().len());
+    }
+}
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+    let main = jl.main().clone();
+
+    jl.register_fn(&main, "cache_set", |args| {
+        let cache = RustBox::<Cache>::downcast(&args[0])?;
+        let key = String::try_from(&args[1])?;
+        let value = i64::try_from(&args[2])?;
+        cache.get()?.entries.borrow_mut().insert(key, value);
+        Ok(Value::nothing())
+    }).unwrap();
+
+    jl.register_fn(&main, "cache_get", |args| {
+        let cache = RustBox::<Cache>::downcast(&args[0])?;
+        let key = String::try_from(&args[1])?;
+        let value = cache.get()?.entries.borrow().get(&key).cloned();
+        Ok(value.map(Value::from).unwrap_or_else(Value::nothing))
+    }).unwrap();
+
+    let cache = RustBox::new(Cache { entries: RefCell::new(HashMap::new()) }).unwrap();
+    main.set("cache", cache.value()).unwrap();
+
+    let ret = jl.eval_string("cache_set(cache, \"answer\", 42); cache_get(cache, \"answer\")")
+        .unwrap();
+    assert_eq!(i64::try_from(&ret).unwrap(), 42);
+
+    // passing anything else is caught by the type check
+    assert!(jl.eval_string("cache_get(1, \"answer\")").is_err());
+
+    // the cache is dropped once Julia collects it
+    drop(cache);
+    main.set("cache", &Value::nothing()).unwrap();
+    jl.gc_mut().collect(true).unwrap();
+}
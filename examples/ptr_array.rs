@@ -0,0 +1,32 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::convert::TryFrom;
+
+use julia::api::{Julia, Value, JlValue, Array};
+
+fn main() {
+    let jl = Julia::new().unwrap();
+    let sum = jl.base().function("sum").unwrap();
+
+    // borrowed: Julia sees the Rust buffer for as long as `signal` lives.
+    let mut signal: Vec<f64> = (0..1000).map(|i| i as f64).collect();
+    {
+        // neither `sum` nor `fill!` keep a reference to the array, and the
+        // handles don't leave this block.
+        let array = unsafe { Array::borrow_slice(&mut signal) }.unwrap();
+        let array = array.to_value().unwrap();
+        let total = sum.call(&[array.clone()]).unwrap();
+        println!("sum(signal) = {}", f64::try_from(&total).unwrap());
+
+        let fill = jl.base().function("fill!").unwrap();
+        fill.call(&[array.clone(), Value::from(0.5)]).unwrap();
+    }
+    assert!(signal.iter().all(|&x| (x - 0.5).abs() < std::f64::EPSILON));
+
+    // owned: the buffer moves into Julia and is freed when it's collected.
+    let matrix = vec![1i32, 2, 3, 4, 5, 6].into_boxed_slice();
+    let matrix = Array::from_boxed_slice_with_dims(matrix, &[2, 3]).unwrap();
+    println!("{}", matrix);
+}
@@ -0,0 +1,29 @@
+#![feature(try_from)]
+
+extern crate julia;
+
+use std::convert::TryFrom;
+
+use julia::api::Julia;
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    let sum = jl.scope(|jl, scope| {
+        let xs = scope.root(jl.eval_string("collect(1.0:100.0)")?)?;
+        let ys = scope.root(jl.eval_string("collect(100.0:-1.0:1.0)")?)?;
+
+        // xs and ys survive a full collection, because they're rooted.
+        jl.gc_mut().collect(true)?;
+
+        let plus = jl.base().function("+")?;
+        let sum = jl.base().function("sum")?;
+        let zs = scope.root(plus.call(&[xs.get().clone(), ys.get().clone()])?)?;
+        let total = sum.call(&[zs.get().clone()])?;
+        f64::try_from(&total)
+    });
+
+    let sum = sum.unwrap();
+    assert!((sum - 10100.0).abs() < std::f64::EPSILON);
+    println!("sum = {}", sum);
+}
@@ -0,0 +1,71 @@
+extern crate julia;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::collections::HashMap;
+
+use julia::api::Julia;
+use julia::api::serde::{from_value, to_value, Serializer, StructRepr};
+use serde::Serialize;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Solver {
+    Newton,
+    Bisection { tolerance: f64 },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    iterations: u32,
+    weights: Vec<f64>,
+    seed: Option<u64>,
+    solver: Solver,
+    labels: HashMap<String, i64>,
+}
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    let mut labels = HashMap::new();
+    labels.insert("a".to_string(), 1);
+    labels.insert("b".to_string(), 2);
+
+    let config = Config {
+        name: "run".to_string(),
+        iterations: 10,
+        weights: vec![0.5, 1.5],
+        seed: None,
+        solver: Solver::Bisection { tolerance: 1e-6 },
+        labels: labels,
+    };
+
+    let value = to_value(&config).unwrap();
+    jl.main().set("config", &value).unwrap();
+    jl.eval_string("@assert config.weights == [0.5, 1.5]").unwrap();
+    jl.eval_string("@assert config.seed === nothing").unwrap();
+    jl.eval_string("@assert config.labels[\"b\"] == 2").unwrap();
+    jl.eval_string("@assert config.solver.Bisection.tolerance == 1e-6").unwrap();
+
+    // round trip
+    let back: Config = from_value(&value).unwrap();
+    assert_eq!(back, config);
+
+    // structs as Dicts
+    let serializer = Serializer::new().structs(StructRepr::Dict);
+    let value = config.serialize(serializer).unwrap();
+    jl.main().set("config", &value).unwrap();
+    jl.eval_string("@assert config[\"iterations\"] == 10").unwrap();
+    let back: Config = from_value(&value).unwrap();
+    assert_eq!(back, config);
+
+    // Julia values can be read too, unit variants are symbols
+    let value = jl.eval_string(
+        "(name = \"jl\", iterations = 3, weights = Float64[], seed = 42, solver = :Newton,
+          labels = Dict(\"x\" => 0))",
+    ).unwrap();
+    let config: Config = from_value(&value).unwrap();
+    assert_eq!(config.seed, Some(42));
+    assert_eq!(config.solver, Solver::Newton);
+}
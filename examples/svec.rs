@@ -1,8 +1,7 @@
-
 #[macro_use]
 extern crate julia;
 
-use julia::api::Julia;
+use julia::api::{Julia, Value, Array, Svec};
 
 fn main() {
     let mut _jl = Julia::new().unwrap();
@@ -11,4 +10,14 @@ fn main() {
     println!("{}", jlvec![1, 2].unwrap());
     println!("{}", jlvec![1, 2, 3, 4].unwrap());
     println!("{}", jlvec![1; 8].unwrap());
+    println!("{}", jlvec![in (0..8).map(|i| i * i)].unwrap());
+
+    let svec: Svec = (0..4).map(Value::from).collect();
+    for x in &svec {
+        println!("{}", x.unwrap());
+    }
+
+    let array: Array = svec.iter().rev().map(Result::unwrap).collect();
+    println!("{}", array);
+    assert_eq!(array.iter().len(), 4);
 }
@@ -0,0 +1,42 @@
+extern crate julia;
+
+use julia::api::{Julia, JlValue, Array, Datatype};
+
+fn main() {
+    let mut jl = Julia::new().unwrap();
+
+    let matrix = jl.eval_string("Float64[1 2 3; 4 5 6]").unwrap();
+    let matrix = Array::from_value(matrix).unwrap();
+    let mut matrix = matrix.typed::<f64>().unwrap();
+
+    println!("dims = {:?}", matrix.dims().unwrap());
+    // column-major: [1.0, 4.0, 2.0, 5.0, 3.0, 6.0]
+    println!("data = {:?}", matrix.to_vec().unwrap());
+
+    assert!((matrix.get(&[1, 2]).unwrap() - 6.0).abs() < std::f64::EPSILON);
+    matrix.set(&[0, 0], 10.0).unwrap();
+
+    // no other handle touches the matrix while the slice is alive
+    for x in unsafe { matrix.as_mut_slice() }.unwrap() {
+        *x *= 2.0;
+    }
+    println!("{}", matrix.array());
+
+    assert!(matrix.array().typed::<i32>().is_err());
+
+    // Matrix{Float64} allocated from Rust, reshaped into a Vector{Float64}.
+    let zeros = Array::alloc_2d(&Datatype::float64(), 3, 2).unwrap();
+    let mut zeros = zeros.typed::<f64>().unwrap();
+    for x in unsafe { zeros.as_mut_slice() }.unwrap() {
+        *x = 0.0;
+    }
+    assert_eq!(zeros.array().size(0).unwrap(), 3);
+    assert_eq!(zeros.array().size(1).unwrap(), 2);
+
+    let flat = zeros.array().reshape(&[6]).unwrap();
+    assert_eq!(flat.dims().unwrap(), vec![6]);
+    assert!(zeros.array().reshape(&[4]).is_err());
+
+    let cube = Array::with_dims(&Datatype::int32(), &[2, 2, 2]).unwrap();
+    println!("size(cube) = {:?}", cube.dims().unwrap());
+}
@@ -0,0 +1,29 @@
+extern crate julia;
+
+use julia::api::{Julia, Value, Array, Datatype};
+
+fn main() {
+    let _jl = Julia::new().unwrap();
+
+    let v = Array::alloc_1d(&Datatype::int64(), 0).unwrap();
+    v.extend((1..6i64).map(Value::from)).unwrap();
+    v.push(&Value::from(6i64)).unwrap();
+    v.insert(0, &Value::from(0i64)).unwrap();
+    println!("{}", v);
+    // [0, 1, 2, 3, 4, 5, 6]
+
+    let last = v.pop().unwrap().unwrap();
+    println!("popped {}", last);
+
+    v.truncate(3).unwrap();
+    println!("{}", v);
+    // [0, 1, 2]
+
+    // wrong element type
+    assert!(v.push(&Value::from(1.5)).is_err());
+
+    let w = Array::alloc_1d(&Datatype::int64(), 0).unwrap();
+    w.append(&v).unwrap();
+    w.append(&v).unwrap();
+    assert_eq!(w.len().unwrap(), 6);
+}
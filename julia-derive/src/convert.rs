@@ -0,0 +1,402 @@
+//! Implementation of `#[derive(IntoJulia)]` and `#[derive(FromJulia)]`.
+
+use quote::Tokens;
+use syn::{Attribute, Data, DeriveInput, Fields, GenericParam, Ident, Lit, Meta, NestedMeta,
+          TypeParamBound};
+
+/// Options given by `#[julia(...)]` attributes.
+#[derive(Default)]
+struct JuliaAttrs {
+    /// `name = "..."`, the name of the Julia type or field.
+    name: Option<String>,
+    /// `mutable`, whether `#[derive(JuliaType)]` defines a mutable struct.
+    mutable: bool,
+}
+
+/// Parses the `#[julia(...)]` attributes of an item.
+fn julia_attrs(attrs: &[Attribute]) -> JuliaAttrs {
+    let mut julia_attrs = JuliaAttrs::default();
+
+    for meta in attrs.iter().filter_map(Attribute::interpret_meta) {
+        let list = match meta {
+            Meta::List(ref list) if list.ident == "julia" => list,
+            _ => continue,
+        };
+
+        for nested in list.nested.iter() {
+            match *nested {
+                NestedMeta::Meta(Meta::NameValue(ref meta)) if meta.ident == "name" => {
+                    match meta.lit {
+                        Lit::Str(ref name) => julia_attrs.name = Some(name.value()),
+                        _ => panic!("#[julia(name = ...)] expects a string"),
+                    }
+                }
+                NestedMeta::Meta(Meta::Word(ref word)) if *word == "mutable" => {
+                    julia_attrs.mutable = true;
+                }
+                _ => panic!("unknown #[julia] attribute, expected `name = \"...\"` or `mutable`"),
+            }
+        }
+    }
+    julia_attrs
+}
+
+/// Returns the name given by a `#[julia(name = "...")]` attribute.
+fn julia_name(attrs: &[Attribute]) -> Option<String> {
+    julia_attrs(attrs).name
+}
+
+/// Adds `bound` to every type parameter of `input`.
+fn add_bounds(input: &mut DeriveInput, bound: &TypeParamBound) {
+    for param in input.generics.params.iter_mut() {
+        if let GenericParam::Type(ref mut param) = *param {
+            param.bounds.push(bound.clone());
+        }
+    }
+}
+
+/// A struct or an enum variant mapped onto a Julia type.
+struct Shape<'a> {
+    /// Path of the Rust struct or variant, e.g. `Shape::Circle`.
+    path: Tokens,
+    /// Path of the Julia type relative to `Main`.
+    julia_name: String,
+    fields: &'a Fields,
+}
+
+impl<'a> Shape<'a> {
+    fn bindings(&self) -> Vec<Ident> {
+        (0..self.fields.iter().count())
+            .map(|i| Ident::from(format!("__field{}", i)))
+            .collect()
+    }
+
+    /// Returns the Julia names of the fields. Fields of tuple structs are
+    /// called `_1`, `_2` and so on by default.
+    fn field_names(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| match (julia_name(&field.attrs), field.ident) {
+                (Some(name), _) => name,
+                (None, Some(ident)) => ident.as_ref().to_string(),
+                (None, None) => format!("_{}", i + 1),
+            })
+            .collect()
+    }
+
+    /// Returns a pattern binding every field to `__field{i}`.
+    fn pattern(&self) -> Tokens {
+        let path = &self.path;
+        let bindings = self.bindings();
+
+        match *self.fields {
+            Fields::Named(ref fields) => {
+                let idents = fields.named.iter().map(|field| field.ident.unwrap());
+                quote! { #path { #( #idents: #bindings ),* } }
+            }
+            Fields::Unnamed(_) => quote! { #path ( #( #bindings ),* ) },
+            Fields::Unit => quote! { #path },
+        }
+    }
+
+    /// Returns an expression converting the bound fields into a new instance
+    /// of the Julia type.
+    fn into_julia(&self) -> Tokens {
+        let julia_name = &self.julia_name;
+        let bindings = self.bindings();
+
+        let new_struct = match *self.fields {
+            _ if self.fields.iter().next().is_none() => {
+                quote! { ::julia::api::convert::new_struct_checked(&datatype, &[]) }
+            }
+            Fields::Named(_) => {
+                let names = self.field_names();
+                quote! {
+                    let fields: &[(&str, ::julia::api::Value)] = &[
+                        #(
+                            (
+                                #names,
+                                scope.root(::julia::api::IntoJulia::into_julia(#bindings)?)?
+                                    .get()
+                                    .clone(),
+                            ),
+                        )*
+                    ];
+                    ::julia::api::convert::new_struct_by_name(&datatype, fields)
+                }
+            }
+            Fields::Unnamed(_) | Fields::Unit => {
+                quote! {
+                    let fields: &[::julia::api::Value] = &[
+                        #(
+                            scope.root(::julia::api::IntoJulia::into_julia(#bindings)?)?
+                                .get()
+                                .clone(),
+                        )*
+                    ];
+                    ::julia::api::convert::new_struct_checked(&datatype, fields)
+                }
+            }
+        };
+
+        // Without fields there's nothing to root.
+        let new_struct = if self.fields.iter().next().is_none() {
+            new_struct
+        } else {
+            quote! { ::julia::api::Scope::enter(|scope| { #new_struct }) }
+        };
+
+        quote! {
+            {
+                let datatype = ::julia::api::convert::lookup_datatype(#julia_name)?;
+                #new_struct
+            }
+        }
+    }
+
+    /// Returns an expression converting `value`, which must be an instance
+    /// of `datatype`, into the Rust struct or variant.
+    fn from_julia(&self) -> Tokens {
+        let path = &self.path;
+
+        match *self.fields {
+            _ if self.fields.iter().next().is_none() => {
+                let pattern = self.pattern();
+                quote! {
+                    {
+                        ::julia::api::convert::check_tuple_struct(value, &datatype, 0)?;
+                        Ok(#pattern)
+                    }
+                }
+            }
+            Fields::Named(ref fields) => {
+                let idents = fields.named.iter().map(|field| field.ident.unwrap());
+                let names1 = self.field_names();
+                let names2 = self.field_names();
+                quote! {
+                    {
+                        ::julia::api::convert::check_struct(value, &datatype, &[#( #names1 ),*])?;
+                        ::julia::api::Scope::enter(|scope| {
+                            Ok(#path {
+                                #(
+                                    #idents: ::julia::api::FromJulia::from_julia(
+                                        scope.root(::julia::api::convert::field(value, #names2)?)?
+                                            .get(),
+                                    )?,
+                                )*
+                            })
+                        })
+                    }
+                }
+            }
+            Fields::Unnamed(ref fields) => {
+                let nfields = fields.unnamed.len();
+                let indices = 0..nfields;
+                quote! {
+                    {
+                        ::julia::api::convert::check_tuple_struct(value, &datatype, #nfields)?;
+                        ::julia::api::Scope::enter(|scope| {
+                            Ok(#path (
+                                #(
+                                    {
+                                        let field =
+                                            ::julia::api::convert::field_at(value, #indices)?;
+                                        ::julia::api::FromJulia::from_julia(
+                                            scope.root(field)?.get(),
+                                        )?
+                                    },
+                                )*
+                            ))
+                        })
+                    }
+                }
+            }
+            Fields::Unit => unreachable!(),
+        }
+    }
+}
+
+/// Derives `IntoJulia`.
+pub fn derive_into_julia(mut input: DeriveInput) -> Tokens {
+    add_bounds(&mut input, &parse_quote!(::julia::api::IntoJulia));
+    let ident = input.ident;
+
+    let arms: Vec<Tokens> = match input.data {
+        Data::Struct(ref data) => {
+            let shape = Shape {
+                path: quote! { #ident },
+                julia_name: julia_name(&input.attrs).unwrap_or_else(|| ident.as_ref().to_string()),
+                fields: &data.fields,
+            };
+            let pattern = shape.pattern();
+            let into_julia = shape.into_julia();
+            vec![quote! { #pattern => #into_julia }]
+        }
+        Data::Enum(ref data) => data.variants
+            .iter()
+            .map(|variant| {
+                let variant_ident = variant.ident;
+                let shape = Shape {
+                    path: quote! { #ident::#variant_ident },
+                    julia_name: julia_name(&variant.attrs)
+                        .unwrap_or_else(|| variant_ident.as_ref().to_string()),
+                    fields: &variant.fields,
+                };
+                let pattern = shape.pattern();
+
+                // Unit variants become symbols, all others Julia structs.
+                if let Fields::Unit = variant.fields {
+                    let name = &shape.julia_name;
+                    quote! { #pattern => ::julia::api::convert::symbol(#name) }
+                } else {
+                    let into_julia = shape.into_julia();
+                    quote! { #pattern => #into_julia }
+                }
+            })
+            .collect(),
+        Data::Union(_) => panic!("#[derive(IntoJulia)] can't be used on unions"),
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::julia::api::IntoJulia for #ident #ty_generics #where_clause {
+            fn into_julia(self) -> ::julia::error::Result<::julia::api::Value> {
+                match self {
+                    #( #arms, )*
+                }
+            }
+        }
+    }
+}
+
+/// Derives `FromJulia`.
+pub fn derive_from_julia(mut input: DeriveInput) -> Tokens {
+    add_bounds(&mut input, &parse_quote!(::julia::api::FromJulia));
+    let ident = input.ident;
+
+    let body = match input.data {
+        Data::Struct(ref data) => {
+            let shape = Shape {
+                path: quote! { #ident },
+                julia_name: julia_name(&input.attrs).unwrap_or_else(|| ident.as_ref().to_string()),
+                fields: &data.fields,
+            };
+            let julia_name = &shape.julia_name;
+            let from_julia = shape.from_julia();
+            quote! {
+                let datatype = ::julia::api::convert::lookup_datatype(#julia_name)?;
+                #from_julia
+            }
+        }
+        Data::Enum(ref data) => {
+            let mut symbols = vec![];
+            let mut structs = vec![];
+
+            for variant in data.variants.iter() {
+                let variant_ident = variant.ident;
+                let shape = Shape {
+                    path: quote! { #ident::#variant_ident },
+                    julia_name: julia_name(&variant.attrs)
+                        .unwrap_or_else(|| variant_ident.as_ref().to_string()),
+                    fields: &variant.fields,
+                };
+                let julia_name = &shape.julia_name;
+
+                if let Fields::Unit = variant.fields {
+                    let path = &shape.path;
+                    symbols.push(quote! { #julia_name => return Ok(#path), });
+                } else {
+                    let from_julia = shape.from_julia();
+                    structs.push(quote! {
+                        let datatype = ::julia::api::convert::lookup_datatype(#julia_name)?;
+                        if value.isa(&datatype)? {
+                            return #from_julia;
+                        }
+                    });
+                }
+            }
+
+            let symbols = if symbols.is_empty() {
+                quote!{}
+            } else {
+                quote! {
+                    if let Some(name) = ::julia::api::convert::symbol_name(value)? {
+                        match name.as_str() {
+                            #( #symbols )*
+                            _ => {}
+                        }
+                    }
+                }
+            };
+
+            quote! {
+                #symbols
+                #( #structs )*
+                Err(::julia::error::Error::TypeMismatch)
+            }
+        }
+        Data::Union(_) => panic!("#[derive(FromJulia)] can't be used on unions"),
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::julia::api::FromJulia for #ident #ty_generics #where_clause {
+            fn from_julia(value: &::julia::api::Value) -> ::julia::error::Result<Self> {
+                #body
+            }
+        }
+    }
+}
+
+/// Derives `JuliaType`.
+pub fn derive_julia_type(input: DeriveInput) -> Tokens {
+    if !input.generics.params.is_empty() {
+        panic!("#[derive(JuliaType)] can't be used on generic types");
+    }
+    let ident = input.ident;
+    let attrs = julia_attrs(&input.attrs);
+
+    let shape = match input.data {
+        Data::Struct(ref data) => Shape {
+            path: quote! { #ident },
+            julia_name: attrs.name.unwrap_or_else(|| ident.as_ref().to_string()),
+            fields: &data.fields,
+        },
+        _ => panic!("#[derive(JuliaType)] can only be used on structs"),
+    };
+
+    let julia_name = &shape.julia_name;
+    let mutable = attrs.mutable;
+    let names = shape.field_names();
+    let types = shape.fields.iter().map(|field| &field.ty);
+
+    quote! {
+        impl ::julia::api::JuliaType for #ident {
+            #[allow(unused_variables)]
+            fn julia_type(
+                jl: &::julia::api::Julia,
+            ) -> ::julia::error::Result<::julia::api::datatype::Type> {
+                ::julia::api::convert::cached_type::<Self, _>(|| {
+                    // Field types like unions aren't cached by Julia.
+                    ::julia::api::Scope::enter(|scope| {
+                        ::julia::api::convert::define_struct(#julia_name, #mutable, &[
+                            #(
+                                (
+                                    #names,
+                                    scope
+                                        .root(<#types as ::julia::api::JuliaType>::julia_type(jl)?)?
+                                        .get()
+                                        .clone(),
+                                ),
+                            )*
+                        ])
+                    })
+                })?
+                .to_type()
+            }
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Implementation of `#[julia_export]`.
+
+use quote::Tokens;
+use syn::{Attribute, FnArg, FnDecl, Ident, ImplItem, ItemFn, ItemImpl, Lit, Meta, Pat,
+          PathArguments, ReturnType, Type, TypeReference, Visibility, GenericParam};
+use syn::fold::{self, Fold};
+
+/// Replaces type parameters with concrete types and strips the lifetimes of
+/// references, so types can be used outside of the original signature.
+struct Substitute<'a> {
+    params: &'a [(Ident, Type)],
+}
+
+impl<'a> Fold for Substitute<'a> {
+    fn fold_type(&mut self, ty: Type) -> Type {
+        if let Type::Path(ref ty) = ty {
+            if ty.qself.is_none() && ty.path.segments.len() == 1 {
+                let segment = ty.path.segments.iter().next().unwrap();
+                if let PathArguments::None = segment.arguments {
+                    for &(ref param, ref concrete) in self.params {
+                        if segment.ident == *param {
+                            return concrete.clone();
+                        }
+                    }
+                }
+            }
+        }
+        fold::fold_type(self, ty)
+    }
+
+    fn fold_type_reference(&mut self, ty: TypeReference) -> TypeReference {
+        let mut ty = fold::fold_type_reference(self, ty);
+        ty.lifetime = None;
+        ty
+    }
+}
+
+/// A single Julia method calling into Rust.
+struct Export {
+    /// Name of the Julia function.
+    julia_name: String,
+    /// Name of the generated `extern "C"` function.
+    shim: Ident,
+    /// The type of the boxed receiver and the Julia type it's bound as.
+    receiver: Option<(Type, String)>,
+    args: Vec<(Ident, Type)>,
+    ret: Type,
+    /// Expression calling the exported function. The arguments are bound to
+    /// their names and the receiver to `this`.
+    call: Tokens,
+    doc: Option<String>,
+}
+
+impl Export {
+    /// Generates the `extern "C"` function called by Julia.
+    fn shim(&self) -> Tokens {
+        let shim = &self.shim;
+        let ret = &self.ret;
+        let call = &self.call;
+
+        let (receiver, this) = match self.receiver {
+            Some((ref ty, _)) => {
+                let receiver = quote! { this: <#ty as ::julia::ext::ExternArg>::Raw, };
+                let this = quote! {
+                    let this = unsafe { <#ty as ::julia::ext::ExternArg>::from_raw(this)? };
+                };
+                (receiver, this)
+            }
+            None => (quote!{}, quote!{}),
+        };
+
+        let names1 = self.args.iter().map(|arg| &arg.0);
+        let types1 = self.args.iter().map(|arg| &arg.1);
+        let names2 = self.args.iter().map(|arg| &arg.0);
+        let names3 = self.args.iter().map(|arg| &arg.0);
+        let types2 = self.args.iter().map(|arg| &arg.1);
+
+        quote! {
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            extern "C" fn #shim(
+                #receiver
+                #( #names1: <#types1 as ::julia::ext::ExternArg>::Raw ),*
+            ) -> <#ret as ::julia::ext::ExternRet>::Raw {
+                ::julia::ext::catch_extern(|| {
+                    #this
+                    #(
+                        let #names2 = unsafe {
+                            <#types2 as ::julia::ext::ExternArg>::from_raw(#names3)?
+                        };
+                    )*
+                    let ret: #ret = #call;
+                    ::julia::ext::ExternRet::into_raw(ret)
+                })
+            }
+        }
+    }
+
+    /// Generates the code defining the Julia method in `module`.
+    fn register(&self) -> Tokens {
+        let julia_name = &self.julia_name;
+        let shim = &self.shim;
+        let ret = &self.ret;
+
+        let receiver = match self.receiver {
+            Some((ref ty, ref julia_type)) => {
+                quote! {
+                    (
+                        "self",
+                        String::from(#julia_type),
+                        <#ty as ::julia::ext::ExternArg>::ccall_type(),
+                    ),
+                }
+            }
+            None => quote!{},
+        };
+
+        let names = self.args.iter().map(|arg| arg.0.as_ref());
+        let types1 = self.args.iter().map(|arg| &arg.1);
+        let types2 = self.args.iter().map(|arg| &arg.1);
+
+        let decl = match self.doc {
+            Some(ref doc) => {
+                let doc = format!("@doc {} ", julia_string(doc));
+                quote! { format!("{}{}", #doc, decl) }
+            }
+            None => quote! { decl },
+        };
+
+        quote! {
+            {
+                let decl = ::julia::ext::ccall_decl(
+                    #julia_name,
+                    &::julia::ext::ccall_target(#shim as *const ::std::os::raw::c_void),
+                    &[
+                        #receiver
+                        #(
+                            (
+                                #names,
+                                <#types1 as ::julia::ext::ExternArg>::julia_type(),
+                                <#types2 as ::julia::ext::ExternArg>::ccall_type(),
+                            ),
+                        )*
+                    ],
+                    &<#ret as ::julia::ext::ExternRet>::ccall_type(),
+                );
+                module.eval_string(#decl)?;
+            }
+        }
+    }
+}
+
+/// Returns `s` as a Julia string literal.
+fn julia_string(s: &str) -> String {
+    let s = s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "\\$");
+    format!("\"{}\"", s)
+}
+
+/// Joins the doc comments of an item.
+fn doc_string(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter_map(Attribute::interpret_meta)
+        .filter_map(|meta| match meta {
+            Meta::NameValue(ref meta) if meta.ident == "doc" => match meta.lit {
+                Lit::Str(ref doc) => Some(doc.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| {
+            if line.starts_with(' ') {
+                line[1..].to_string()
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// Checks if `ty` is or contains a reference with a `'static` lifetime.
+fn has_static_ref(ty: &Type) -> bool {
+    match *ty {
+        Type::Reference(ref ty) => {
+            let is_static = ty.lifetime
+                .as_ref()
+                .map_or(false, |l| quote!(#l).to_string() == "'static");
+            is_static || has_static_ref(&ty.elem)
+        }
+        Type::Slice(ref ty) => has_static_ref(&ty.elem),
+        Type::Paren(ref ty) => has_static_ref(&ty.elem),
+        _ => false,
+    }
+}
+
+/// Returns the names and types of the arguments, skipping the receiver.
+fn args(decl: &FnDecl, subst: &mut Substitute) -> Vec<(Ident, Type)> {
+    decl.inputs
+        .iter()
+        .filter_map(|arg| match *arg {
+            FnArg::Captured(ref arg) => {
+                let name = match arg.pat {
+                    Pat::Ident(ref pat) => pat.ident,
+                    _ => panic!("#[julia_export] only supports plain identifiers as arguments"),
+                };
+                if has_static_ref(&arg.ty) {
+                    panic!("#[julia_export] can't export functions taking `'static` references, \
+                            arguments are only borrowed for the duration of the call");
+                }
+                Some((name, subst.fold_type(arg.ty.clone())))
+            }
+            FnArg::SelfRef(_) | FnArg::SelfValue(_) => None,
+            _ => panic!("#[julia_export] doesn't support this kind of argument"),
+        })
+        .collect()
+}
+
+/// Returns the return type, `()` if there's none.
+fn ret(decl: &FnDecl, subst: &mut Substitute) -> Type {
+    match decl.output {
+        ReturnType::Default => parse_quote!(()),
+        ReturnType::Type(_, ref ty) => subst.fold_type((**ty).clone()),
+    }
+}
+
+/// Exports a free function, once per instantiation if it's generic.
+pub fn export_fn(item: ItemFn, instances: Vec<Type>) -> Tokens {
+    let name = item.ident;
+    let params: Vec<Ident> = item.decl
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match *param {
+            GenericParam::Type(ref param) => Some(param.ident),
+            _ => None,
+        })
+        .collect();
+
+    if params.is_empty() && !instances.is_empty() {
+        panic!("#[julia_export] takes instantiations only on generic functions");
+    } else if !params.is_empty() && instances.is_empty() {
+        panic!("#[julia_export] needs a list of instantiations for generic functions");
+    }
+
+    // Non-generic functions are exported once, without any substitutions.
+    let instances = if params.is_empty() {
+        vec![None]
+    } else {
+        instances.into_iter().map(Some).collect()
+    };
+
+    let doc = doc_string(&item.attrs);
+    let mut exports = vec![];
+    for (i, instance) in instances.into_iter().enumerate() {
+        let substitutions: Vec<(Ident, Type)> = match instance {
+            None => vec![],
+            Some(Type::Tuple(ref tuple)) if params.len() > 1 => {
+                if tuple.elems.len() != params.len() {
+                    panic!("#[julia_export] got the wrong number of types in an instantiation");
+                }
+                params.iter().cloned().zip(tuple.elems.iter().cloned()).collect()
+            }
+            Some(ty) => {
+                if params.len() != 1 {
+                    panic!("#[julia_export] needs a tuple of types for every instantiation");
+                }
+                vec![(params[0], ty)]
+            }
+        };
+
+        let mut subst = Substitute { params: &substitutions };
+        let args = args(&item.decl, &mut subst);
+        let ret = ret(&item.decl, &mut subst);
+
+        let arg_names = args.iter().map(|arg| &arg.0);
+        let call = if substitutions.is_empty() {
+            quote! { #name(#( #arg_names ),*) }
+        } else {
+            let types = substitutions.iter().map(|sub| &sub.1);
+            quote! { #name::<#( #types ),*>(#( #arg_names ),*) }
+        };
+
+        exports.push(Export {
+            julia_name: name.as_ref().to_string(),
+            shim: Ident::from(format!("__jl_export_{}_{}", name, i)),
+            receiver: None,
+            args: args,
+            ret: ret,
+            call: call,
+            doc: doc.clone(),
+        });
+    }
+
+    let vis = &item.vis;
+    let register = Ident::from(format!("register_{}", name));
+    let shims = exports.iter().map(Export::shim);
+    let registrations = exports.iter().map(Export::register);
+
+    quote! {
+        #item
+
+        #( #shims )*
+
+        /// Defines the exported function in `module`.
+        #vis fn #register(module: &::julia::api::Module) -> ::julia::error::Result<()> {
+            ::julia::ext::load_extern_module()?;
+            #( #registrations )*
+            Ok(())
+        }
+    }
+}
+
+/// Checks if `ty` refers to the type an impl block is for.
+fn is_self(ty: &Type, type_name: &Ident) -> bool {
+    match *ty {
+        Type::Path(ref ty) if ty.qself.is_none() && ty.path.segments.len() == 1 => {
+            let segment = ty.path.segments.iter().next().unwrap();
+            segment.ident == "Self" || segment.ident == *type_name
+        }
+        _ => false,
+    }
+}
+
+/// Exports the public methods of an impl block.
+pub fn export_impl(item: ItemImpl) -> Tokens {
+    if item.trait_.is_some() {
+        panic!("#[julia_export] can't be used on trait impls");
+    }
+    if !item.generics.params.is_empty() {
+        panic!("#[julia_export] can't be used on generic impl blocks");
+    }
+
+    let self_ty = (*item.self_ty).clone();
+    let type_name = match self_ty {
+        Type::Path(ref ty) => ty.path.segments.iter().last().unwrap().ident,
+        _ => panic!("#[julia_export] can only be used on impl blocks of named types"),
+    };
+    let boxed: Type = parse_quote!(::julia::api::RustBox<#self_ty>);
+
+    let self_param = (Ident::from("Self"), self_ty.clone());
+    let self_params = [self_param];
+
+    let mut exports = vec![];
+    for impl_item in &item.items {
+        let method = match *impl_item {
+            ImplItem::Method(ref method) => method,
+            _ => continue,
+        };
+        match method.vis {
+            Visibility::Public(_) => {}
+            _ => continue,
+        }
+
+        let method_name = method.sig.ident;
+        let decl = &method.sig.decl;
+        if !decl.generics.params.is_empty() {
+            panic!("#[julia_export] can't export generic methods");
+        }
+
+        let receiver = match decl.inputs.iter().next() {
+            Some(&FnArg::SelfRef(ref receiver)) => {
+                if receiver.mutability.is_some() {
+                    panic!("#[julia_export] can't export methods taking `&mut self`, use interior mutability");
+                }
+                Some((boxed.clone(), type_name.as_ref().to_string()))
+            }
+            Some(&FnArg::SelfValue(_)) => {
+                panic!("#[julia_export] can't export methods taking `self` by value");
+            }
+            _ => None,
+        };
+
+        let mut subst = Substitute { params: &self_params };
+        let args = args(decl, &mut subst);
+        let ret = ret(decl, &mut subst);
+
+        let arg_names = args.iter().map(|arg| &arg.0);
+        let call = if receiver.is_some() {
+            quote! { <#self_ty>::#method_name(&this.get()?, #( #arg_names ),*) }
+        } else {
+            quote! { <#self_ty>::#method_name(#( #arg_names ),*) }
+        };
+
+        let (ret, call) = if is_self(&ret, &type_name) {
+            (boxed.clone(), quote! { ::julia::api::RustBox::new(#call)? })
+        } else {
+            (ret, call)
+        };
+
+        let julia_name = if method_name == "new" {
+            type_name.as_ref().to_string()
+        } else {
+            method_name.as_ref().to_string()
+        };
+
+        exports.push(Export {
+            julia_name: julia_name,
+            shim: Ident::from(format!("__jl_export_{}_{}", type_name, method_name)),
+            receiver: receiver,
+            args: args,
+            ret: ret,
+            call: call,
+            doc: doc_string(&method.attrs),
+        });
+    }
+
+    let type_name = type_name.as_ref();
+    let shims = exports.iter().map(Export::shim);
+    let registrations = exports.iter().map(Export::register);
+
+    quote! {
+        #item
+
+        #( #shims )*
+
+        impl #self_ty {
+            /// Binds the Julia type wrapping this type and defines the
+            /// exported methods in `module`.
+            pub fn register_julia(module: &::julia::api::Module) -> ::julia::error::Result<()> {
+                ::julia::ext::load_extern_module()?;
+
+                if module.global(#type_name).is_err() {
+                    let datatype = ::julia::api::RustBox::<#self_ty>::datatype()?;
+                    let datatype = ::julia::api::JlValue::to_value(&datatype)?;
+                    module.set_const(#type_name, &datatype)?;
+                }
+
+                #( #registrations )*
+                Ok(())
+            }
+        }
+    }
+}
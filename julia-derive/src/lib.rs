@@ -0,0 +1,138 @@
+//! Procedural macros for [julia-rs](https://crates.io/crates/julia).
+//!
+//! # Example
+//!
+//! ```
+//! #![feature(proc_macro)]
+//!
+//! extern crate julia;
+//! extern crate julia_derive;
+//!
+//! use julia::api::Julia;
+//! use julia_derive::julia_export;
+//!
+//! /// Squares a number.
+//! #[julia_export(f32, f64)]
+//! pub fn square<T: Copy + std::ops::Mul<Output = T>>(x: T) -> T {
+//!     x * x
+//! }
+//!
+//! fn main() {
+//!     let mut jl = Julia::new().unwrap();
+//!     register_square(jl.main()).unwrap();
+//!     jl.eval_string("@assert square(2.0) == 4.0").unwrap();
+//! }
+//! ```
+
+#![feature(proc_macro)]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+mod convert;
+mod export;
+
+use proc_macro::TokenStream;
+use syn::{DeriveInput, Item, Type};
+use syn::punctuated::Punctuated;
+use syn::synom::Parser;
+
+/// Exports a function or the public methods of an impl block to Julia.
+///
+/// Arguments and return values are converted with julia-rs' `ExternArg` and
+/// `ExternRet`, so the same types as with `extern_jl!` are supported. Doc
+/// comments become Julia docstrings. A panic or an `Err` returned from the
+/// function is thrown as a Julia exception. Borrowed arguments such as `&str`
+/// or slices are only valid during the call, so they can't be `'static`.
+///
+/// ## Functions
+///
+/// A function `foo` gets a companion `register_foo(module: &Module)` with the
+/// same visibility, which defines `foo` in the Julia module. Generic
+/// functions are exported once per instantiation listed in the attribute,
+/// e.g. `#[julia_export(f32, f64)]`, as methods of the same Julia function.
+/// Functions with several type parameters take tuples, e.g.
+/// `#[julia_export((f64, i64))]`.
+///
+/// ## Impl blocks
+///
+/// The type gets an associated `register_julia(module: &Module)`, which binds
+/// the Julia type wrapping a `RustBox` of it under the name of the Rust type
+/// and defines its public methods. Methods taking `&self` take the boxed
+/// value as their first argument, methods returning `Self` return a new
+/// boxed value. A method called `new` becomes a constructor of the Julia
+/// type.
+#[proc_macro_attribute]
+pub fn julia_export(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let parser = Punctuated::<Type, Token![,]>::parse_terminated;
+    let instances = parser
+        .parse(attr)
+        .expect("expected a list of instantiations");
+    let instances: Vec<Type> = instances.into_iter().collect();
+
+    let item: Item = syn::parse(item).expect("couldn't parse item");
+    let tokens = match item {
+        Item::Fn(item) => export::export_fn(item, instances),
+        Item::Impl(item) => {
+            if !instances.is_empty() {
+                panic!("#[julia_export] takes no arguments on impl blocks");
+            }
+            export::export_impl(item)
+        }
+        _ => panic!("#[julia_export] can only be used on functions and impl blocks"),
+    };
+
+    tokens.into()
+}
+
+/// Derives `IntoJulia`, converting a struct into an instance of a Julia
+/// struct.
+///
+/// Fields are mapped by name onto the fields of the Julia type with the same
+/// name as the Rust type, tuple structs are mapped by position. The Julia
+/// type is looked up relative to `Main` and can be changed with
+/// `#[julia(name = "Module.Type")]`, the name of a field with
+/// `#[julia(name = "field")]`. The field types are checked against the Julia
+/// type when converting, nested structs work as long as they implement
+/// `IntoJulia` themselves.
+///
+/// Unit variants of an enum become symbols, other variants are mapped onto
+/// the Julia struct named after the variant.
+#[proc_macro_derive(IntoJulia, attributes(julia))]
+pub fn derive_into_julia(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).expect("couldn't parse item");
+    convert::derive_into_julia(input).into()
+}
+
+/// Derives `FromJulia`, converting an instance of a Julia struct back into a
+/// Rust value.
+///
+/// Takes the same attributes as `#[derive(IntoJulia)]`. Converting fails if
+/// the value isn't an instance of the Julia type or if its fields don't
+/// match the fields of the Rust type.
+#[proc_macro_derive(FromJulia, attributes(julia))]
+pub fn derive_from_julia(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).expect("couldn't parse item");
+    convert::derive_from_julia(input).into()
+}
+
+/// Derives `JuliaType`, defining a Julia struct with the same fields as a
+/// Rust struct.
+///
+/// The Julia type is defined on the first call to `julia_type` and cached
+/// afterwards. Its name and the names of the fields can be changed with
+/// `#[julia(name = "...")]` as with `#[derive(IntoJulia)]`, the fields of
+/// tuple structs are called `_1`, `_2` and so on. `#[julia(mutable)]` defines
+/// a mutable struct. Every field type has to implement `JuliaType` as well.
+///
+/// If the name is already bound to a struct with the same fields, that
+/// struct is used instead, otherwise defining the type fails.
+#[proc_macro_derive(JuliaType, attributes(julia))]
+pub fn derive_julia_type(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).expect("couldn't parse item");
+    convert::derive_julia_type(input).into()
+}
@@ -184,6 +184,11 @@ pub unsafe fn jl_array_data_owner(a: *mut jl_array_t) -> *mut jl_value_t {
     *((a as *mut c_char).offset(jl_array_data_owner_offset(jl_array_ndims(a)) as isize) as *mut *mut jl_value_t)
 }
 
+extern "C" {
+    // Exported by libjulia, but not declared in julia.h.
+    pub fn jl_array_grow_at(a: *mut jl_array_t, idx: isize, inc: usize);
+}
+
 pub unsafe fn jl_array_ptr_ref<T>(a: *mut T, i: usize) -> *mut jl_value_t {
     assert!(i < jl_array_len(a as *mut jl_array_t));
     *((jl_array_data(a as *mut jl_array_t) as *mut *mut jl_value_t).offset(i as isize))
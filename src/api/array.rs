@@ -2,10 +2,16 @@
 //! Module providing wrappers for iteratable sequences.
 
 use std::slice;
+use std::mem;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::os::raw::c_void;
+use std::iter::FromIterator;
 
 use sys::*;
-use error::Result;
-use api::{Value, JlValue};
+use error::{Result, Error};
+use api::{Value, JlValue, Datatype, Module, Scope};
+use api::datatype::IsBits;
 
 jlvalues! {
     pub struct Array(jl_array_t);
@@ -13,7 +19,253 @@ jlvalues! {
     pub struct Svec(jl_svec_t);
 }
 
+/// Creates a new `NTuple{N, Int}` holding the dimensions `dims`.
+fn dims_tuple(dims: &[usize]) -> Result<Value> {
+    let tt = unsafe { jl_tupletype_fill(dims.len(), jl_long_type as *mut jl_value_t) };
+    jl_catch!();
+    let raw = unsafe { jl_new_bits(tt, dims.as_ptr() as *mut _) };
+    jl_catch!();
+    Value::new(raw)
+}
+
+/// Returns the number of elements described by `dims`.
+///
+/// ## Errors
+///
+/// Returns Error::DimensionMismatch if the number overflows.
+fn dims_len(dims: &[usize]) -> Result<usize> {
+    dims.iter()
+        .fold(Some(1usize), |len, &dim| len.and_then(|len| len.checked_mul(dim)))
+        .ok_or(Error::DimensionMismatch)
+}
+
+/// Name of the private module holding the finalizer type for owned buffers.
+const ARRAYS_MODULE: &str = "__JuliaRsArrays";
+
+/// Source of the private module holding the finalizer type for owned buffers.
+/// A `BufferFinalizer` calls back into Rust to free the buffer it describes.
+const ARRAYS_JL: &str = "module __JuliaRsArrays
+struct BufferFinalizer
+    drop::Ptr{Void}
+    data::Ptr{Void}
+    len::UInt
+end
+(f::BufferFinalizer)(_) = ccall(f.drop, Void, (Ptr{Void}, UInt), f.data, f.len)
+end";
+
+/// Frees a buffer previously leaked by `Array::from_boxed_slice`.
+extern "C" fn drop_buffer<T>(data: *mut T, len: usize) {
+    unsafe {
+        let slice = slice::from_raw_parts_mut(data, len) as *mut [T];
+        drop(Box::from_raw(slice));
+    }
+}
+
 impl Array {
+    /// Allocates a new 1-dimensional Array with element type `eltype` and
+    /// `nr` elements.
+    pub fn alloc_1d(eltype: &Datatype, nr: usize) -> Result<Array> {
+        let atype = eltype.array_type(1)?;
+        let raw = unsafe { jl_alloc_array_1d(atype.lock()? as *mut _, nr) };
+        jl_catch!();
+        Array::new(raw)
+    }
+
+    /// Allocates a new 2-dimensional Array with element type `eltype`, `nr`
+    /// rows and `nc` columns.
+    pub fn alloc_2d(eltype: &Datatype, nr: usize, nc: usize) -> Result<Array> {
+        let atype = eltype.array_type(2)?;
+        let raw = unsafe { jl_alloc_array_2d(atype.lock()? as *mut _, nr, nc) };
+        jl_catch!();
+        Array::new(raw)
+    }
+
+    /// Allocates a new 3-dimensional Array with element type `eltype` and
+    /// dimensions `nr`x`nc`x`z`.
+    pub fn alloc_3d(eltype: &Datatype, nr: usize, nc: usize, z: usize) -> Result<Array> {
+        let atype = eltype.array_type(3)?;
+        let raw = unsafe { jl_alloc_array_3d(atype.lock()? as *mut _, nr, nc, z) };
+        jl_catch!();
+        Array::new(raw)
+    }
+
+    /// Allocates a new Array with element type `eltype` and an arbitrary
+    /// number of dimensions.
+    pub fn with_dims(eltype: &Datatype, dims: &[usize]) -> Result<Array> {
+        let atype = eltype.array_type(dims.len())?;
+        Scope::enter(|scope| {
+            let dims = scope.root(dims_tuple(dims)?)?;
+            let raw = unsafe { jl_new_array(atype.lock()? as *mut _, dims.lock()?) };
+            jl_catch!();
+            Array::new(raw)
+        })
+    }
+
+    /// Constructs a new Array sharing the data of this one, but with
+    /// different dimensions.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::DimensionMismatch if the new dimensions don't describe
+    /// the same number of elements.
+    pub fn reshape(&self, dims: &[usize]) -> Result<Array> {
+        if dims_len(dims)? != self.len()? {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let atype = self.eltype()?.array_type(dims.len())?;
+        Scope::enter(|scope| {
+            let dims = scope.root(dims_tuple(dims)?)?;
+            let raw = unsafe {
+                jl_reshape_array(atype.lock()? as *mut _, self.lock()?, dims.lock()?)
+            };
+            jl_catch!();
+            Array::new(raw)
+        })
+    }
+
+    /// Wraps a mutable slice as a 1-dimensional Array without copying.
+    ///
+    /// The returned `BorrowedArray` borrows `data`, so it can't be used from
+    /// Rust after the slice is gone.
+    ///
+    /// This function is unsafe, because the borrow only covers the
+    /// `BorrowedArray` itself. Handles obtained through it, e.g. clones of the
+    /// Array or `to_value()`, aren't tied to `data`, and Julia code can keep
+    /// a reference to the Array, e.g. in a global. The caller has to make
+    /// sure that neither is used after `data` is gone.
+    pub unsafe fn borrow_slice<T: IsBits>(data: &mut [T]) -> Result<BorrowedArray<T>> {
+        let atype = T::datatype()?.array_type(1)?;
+        let raw = jl_ptr_to_array_1d(
+            atype.lock()? as *mut _,
+            data.as_mut_ptr() as *mut c_void,
+            data.len(),
+            0,
+        );
+        jl_catch!();
+
+        Ok(BorrowedArray {
+            array: Array::new(raw)?,
+            _data: PhantomData,
+        })
+    }
+
+    /// Wraps a mutable slice as an n-dimensional Array without copying. The
+    /// elements are interpreted in column-major order.
+    ///
+    /// This function is unsafe for the same reasons as `borrow_slice`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::DimensionMismatch if `dims` doesn't describe exactly
+    /// `data.len()` elements.
+    pub unsafe fn borrow_slice_with_dims<'a, T: IsBits>(
+        data: &'a mut [T],
+        dims: &[usize],
+    ) -> Result<BorrowedArray<'a, T>> {
+        if dims_len(dims)? != data.len() {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let atype = T::datatype()?.array_type(dims.len())?;
+        let array = Scope::enter(|scope| {
+            let dims = scope.root(dims_tuple(dims)?)?;
+            let raw = jl_ptr_to_array(
+                atype.lock()? as *mut _,
+                data.as_mut_ptr() as *mut c_void,
+                dims.lock()?,
+                0,
+            );
+            jl_catch!();
+            Array::new(raw)
+        })?;
+
+        Ok(BorrowedArray {
+            array: array,
+            _data: PhantomData,
+        })
+    }
+
+    /// Hands a Vec over to Julia as a 1-dimensional Array without copying the
+    /// elements. See `from_boxed_slice`.
+    pub fn from_vec<T: IsBits>(data: Vec<T>) -> Result<Array> {
+        Array::from_boxed_slice(data.into_boxed_slice())
+    }
+
+    /// Hands a boxed slice over to Julia as a 1-dimensional Array without
+    /// copying. The buffer is freed by Rust when Julia collects the Array.
+    pub fn from_boxed_slice<T: IsBits>(data: Box<[T]>) -> Result<Array> {
+        let len = data.len();
+        Array::from_boxed_slice_with_dims(data, &[len])
+    }
+
+    /// Hands a boxed slice over to Julia as an n-dimensional Array without
+    /// copying. The elements are interpreted in column-major order and the
+    /// buffer is freed by Rust when Julia collects the Array.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::DimensionMismatch if `dims` doesn't describe exactly
+    /// `data.len()` elements.
+    pub fn from_boxed_slice_with_dims<T: IsBits>(data: Box<[T]>, dims: &[usize]) -> Result<Array> {
+        let len = data.len();
+        if dims_len(dims)? != len {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let atype = T::datatype()?.array_type(dims.len())?;
+        let module = Module::main_submodule(ARRAYS_MODULE, ARRAYS_JL)?;
+        let finalizer_type = Datatype::from_value(module.global("BufferFinalizer")?)?;
+
+        Scope::enter(move |scope| {
+            let jl_dims = scope.root(dims_tuple(dims)?)?;
+            let ptr = Box::into_raw(data) as *mut T;
+
+            // From here on Julia owns the buffer, if anything fails it's
+            // leaked rather than freed twice.
+            let raw = unsafe {
+                jl_ptr_to_array(
+                    atype.lock()? as *mut _,
+                    ptr as *mut c_void,
+                    jl_dims.lock()?,
+                    0,
+                )
+            };
+            jl_catch!();
+            let array = scope.root(Array::new(raw)?)?;
+
+            let drop_fn = drop_buffer::<T> as extern "C" fn(*mut T, usize) as *mut c_void;
+            let drop_fn = scope.root(Value::new(unsafe { jl_box_voidpointer(drop_fn) })?)?;
+            let data = scope.root(Value::new(unsafe { jl_box_voidpointer(ptr as *mut c_void) })?)?;
+            let len = scope.root(Value::from(len))?;
+
+            let finalizer = finalizer_type.new_struct(&[
+                drop_fn.get().clone(),
+                data.get().clone(),
+                len.get().clone(),
+            ])?;
+            let finalizer = scope.root(finalizer)?;
+            unsafe {
+                jl_gc_add_finalizer(array.lock()? as *mut _, finalizer.lock()? as *mut _);
+            }
+            jl_catch!();
+
+            Ok(array.get().clone())
+        })
+    }
+
+    /// Returns the size of the Array along dimension `d`. Like Julia's
+    /// `size`, dimensions past the last one have size 1.
+    pub fn size(&self, d: usize) -> Result<usize> {
+        let array = self.lock()?;
+        if d >= unsafe { jl_array_ndims(array) } {
+            return Ok(1);
+        }
+        let size = unsafe { jl_array_size(array as *mut jl_value_t, d as i32) };
+        jl_catch!();
+        Ok(size)
+    }
+
     /// Returns the length of the Array.
     pub fn len(&self) -> Result<usize> {
         let len = unsafe { jl_array_len(self.lock()?) };
@@ -39,11 +291,33 @@ impl Array {
         Ok(ndims)
     }
 
+    /// Returns the sizes of all dimensions of the Array.
+    pub fn dims(&self) -> Result<Vec<usize>> {
+        let array = self.lock()?;
+        let ndims = unsafe { jl_array_ndims(array) };
+        let dims = (0..ndims)
+            .map(|i| unsafe { jl_array_dim(array, i) })
+            .collect();
+        Ok(dims)
+    }
+
+    /// Returns the element type of the Array.
+    pub fn eltype(&self) -> Result<Datatype> {
+        let raw = unsafe { jl_array_eltype(self.lock()? as *mut jl_value_t) };
+        jl_catch!();
+        Datatype::new(raw as *mut jl_datatype_t)
+    }
+
     /// Checks if the Array is empty.
     pub fn is_empty(&self) -> bool {
         self.len().unwrap_or(0) == 0
     }
 
+    /// Views the Array as an Array of a concrete isbits type `T`.
+    pub fn typed<T: IsBits>(&self) -> Result<TypedArray<T>> {
+        TypedArray::new(self.clone())
+    }
+
     /// Constructs a Vec of Values from the Array.
     pub fn as_vec(&self) -> Result<Vec<Value>> {
         let len = self.len()?;
@@ -56,6 +330,217 @@ impl Array {
         Ok(vec)
     }
 
+    /// Checks that the Array is 1-dimensional and that `x` is an instance of
+    /// its element type.
+    fn check_push(&self, x: &Value) -> Result<()> {
+        let array = self.lock()?;
+        if unsafe { jl_array_ndims(array) } != 1 {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let eltype = unsafe { jl_array_eltype(array as *mut jl_value_t) };
+        let isa = unsafe { jl_isa(x.lock()?, eltype as *mut jl_value_t) != 0 };
+        if isa {
+            Ok(())
+        } else {
+            Err(Error::TypeMismatch)
+        }
+    }
+
+    /// Appends an element to the back of a 1-dimensional Array.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::DimensionMismatch if the Array isn't 1-dimensional and
+    /// Error::TypeMismatch if `x` isn't an instance of the element type.
+    pub fn push(&self, x: &Value) -> Result<()> {
+        self.check_push(x)?;
+
+        Scope::enter(|scope| {
+            let x = scope.root(x.clone())?;
+            let array = self.lock()?;
+            let x = x.lock()?;
+
+            if unsafe { (*array).flags.ptrarray() != 0 } {
+                unsafe {
+                    jl_array_ptr_1d_push(array, x);
+                }
+                jl_catch!();
+            } else {
+                unsafe {
+                    jl_array_grow_end(array, 1);
+                }
+                jl_catch!();
+                unsafe {
+                    jl_arrayset(array, x, jl_array_len(array) - 1);
+                }
+                jl_catch!();
+            }
+            Ok(())
+        })
+    }
+
+    /// Appends every element of an iterator to the back of a 1-dimensional
+    /// Array.
+    pub fn extend<I>(&self, iter: I) -> Result<()>
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        for x in iter {
+            self.push(&x)?;
+        }
+        Ok(())
+    }
+
+    /// Appends every element of `other` to the back of a 1-dimensional
+    /// Array.
+    pub fn append(&self, other: &Array) -> Result<()> {
+        let array = self.lock()?;
+        let other_raw = other.lock()?;
+        let both_ptr =
+            unsafe { (*array).flags.ptrarray() != 0 && (*other_raw).flags.ptrarray() != 0 };
+
+        if both_ptr && unsafe { jl_array_eltype(array as *mut _) == jl_any_type as *mut _ } {
+            if unsafe { jl_array_ndims(array) } != 1 {
+                return Err(Error::DimensionMismatch);
+            }
+            unsafe {
+                jl_array_ptr_1d_append(array, other_raw);
+            }
+            jl_catch!();
+            return Ok(());
+        }
+
+        for i in 0..other.len()? {
+            // Boxing an inline element allocates, so it has to be rooted
+            // before `push` allocates again.
+            Scope::enter(|scope| {
+                let raw = unsafe { jl_arrayref(other_raw, i) };
+                jl_catch!();
+                let x = scope.root(Value::new(raw)?)?;
+                self.push(&x)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Removes the last element of a 1-dimensional Array and returns it, or
+    /// None if it's empty.
+    pub fn pop(&self) -> Result<Option<Value>> {
+        let array = self.lock()?;
+        if unsafe { jl_array_ndims(array) } != 1 {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let len = self.len()?;
+        if len == 0 {
+            return Ok(None);
+        }
+
+        Scope::enter(|scope| {
+            let raw = unsafe { jl_arrayref(array, len - 1) };
+            jl_catch!();
+            let last = scope.root(Value::new(raw)?)?;
+
+            unsafe {
+                jl_array_del_end(array, 1);
+            }
+            jl_catch!();
+            Ok(Some(last.get().clone()))
+        })
+    }
+
+    /// Shortens a 1-dimensional Array to `len` elements. Does nothing if
+    /// it's already shorter.
+    pub fn truncate(&self, len: usize) -> Result<()> {
+        let array = self.lock()?;
+        if unsafe { jl_array_ndims(array) } != 1 {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let cur = self.len()?;
+        if len < cur {
+            unsafe {
+                jl_array_del_end(array, cur - len);
+            }
+            jl_catch!();
+        }
+        Ok(())
+    }
+
+    /// Inserts an element at position `idx` of a 1-dimensional Array,
+    /// shifting all elements after it to the back.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::OutOfBounds if `idx > len`, Error::DimensionMismatch if
+    /// the Array isn't 1-dimensional and Error::TypeMismatch if `x` isn't an
+    /// instance of the element type.
+    pub fn insert(&self, idx: usize, x: &Value) -> Result<()> {
+        let len = self.len()?;
+        if idx > len {
+            return Err(Error::OutOfBounds);
+        }
+        self.check_push(x)?;
+
+        Scope::enter(|scope| {
+            let x = scope.root(x.clone())?;
+            let array = self.lock()?;
+            unsafe {
+                jl_array_grow_at(array, idx as isize, 1);
+            }
+            jl_catch!();
+            unsafe {
+                jl_arrayset(array, x.lock()?, idx);
+            }
+            jl_catch!();
+            Ok(())
+        })
+    }
+
+    /// Returns the element at a specified index, boxing it if the Array
+    /// stores its elements inline.
+    pub fn element(&self, idx: usize) -> Result<Value> {
+        if idx >= self.len()? {
+            return Err(Error::OutOfBounds);
+        }
+        let raw = unsafe { jl_arrayref(self.lock()?, idx) };
+        jl_catch!();
+        Value::new(raw)
+    }
+
+    /// Returns an iterator over the elements of the Array.
+    pub fn iter(&self) -> ArrayIter {
+        ArrayIter {
+            seq: self,
+            front: 0,
+            back: self.len().unwrap_or(0),
+        }
+    }
+
+    /// Constructs a new `Vector{Any}` from a sequence of Values. This is the
+    /// fallible counterpart of collecting into an Array, which panics if
+    /// Julia throws.
+    pub fn from_values<I>(values: I) -> Result<Array>
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        Scope::enter(|scope| {
+            let raw = unsafe { jl_alloc_vec_any(0) };
+            jl_catch!();
+            let array = scope.root(Array::new(raw)?)?;
+
+            for x in values {
+                let x = scope.root(x)?;
+                unsafe {
+                    jl_array_ptr_1d_push(array.lock()?, x.lock()?);
+                }
+                jl_catch!();
+            }
+            Ok(array.get().clone())
+        })
+    }
+
     /// Returns the value at a specified index.
     pub fn index(&self, idx: usize) -> Result<Value> {
         let raw = unsafe { jl_array_ptr_ref(self.lock()?, idx) };
@@ -71,6 +556,167 @@ impl Array {
     }
 }
 
+/// An n-dimensional Array with elements of an isbits type `T`, stored inline
+/// in column-major order.
+///
+/// The element type is checked once on construction, so the underlying data
+/// can be viewed as a Rust slice without copying or boxing. Any other handle
+/// to the same Array may still alias the data, which is why the slice views
+/// are unsafe.
+#[derive(Clone)]
+pub struct TypedArray<T: IsBits> {
+    array: Array,
+    _marker: PhantomData<T>,
+}
+
+impl<T: IsBits> TypedArray<T> {
+    /// Wraps `array`, checking that its element type is `T`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::TypeMismatch if the element type differs.
+    pub fn new(array: Array) -> Result<TypedArray<T>> {
+        let raw = array.lock()?;
+        let eltype = unsafe { jl_array_eltype(raw as *mut jl_value_t) as *mut jl_datatype_t };
+        let elsize = unsafe { (*raw).elsize as usize };
+        jl_catch!();
+
+        let expected = T::datatype()?;
+        if eltype != expected.lock()? || elsize != mem::size_of::<T>() {
+            return Err(Error::TypeMismatch);
+        }
+
+        Ok(TypedArray {
+            array: array,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Immutably borrows the untyped Array.
+    pub fn array(&self) -> &Array {
+        &self.array
+    }
+
+    /// Consumes self and returns the untyped Array.
+    pub fn into_array(self) -> Array {
+        self.array
+    }
+
+    /// Returns the total number of elements.
+    pub fn len(&self) -> Result<usize> {
+        self.array.len()
+    }
+
+    /// Checks if the TypedArray is empty.
+    pub fn is_empty(&self) -> bool {
+        self.array.is_empty()
+    }
+
+    /// Returns the sizes of all dimensions.
+    pub fn dims(&self) -> Result<Vec<usize>> {
+        self.array.dims()
+    }
+
+    /// Returns a pointer to the first element.
+    fn data_ptr(&self) -> Result<*mut T> {
+        Ok(unsafe { jl_array_data(self.array.lock()?) as *mut T })
+    }
+
+    /// Constructs a slice of all elements in column-major order without
+    /// copying.
+    ///
+    /// This function is unsafe, because the slice isn't tied to the Array
+    /// itself. Other handles to the same Array, e.g. clones of this one, and
+    /// Julia code can still write to the data or resize the Array, which may
+    /// reallocate it, while the slice is alive. The caller has to make sure
+    /// that neither happens until the slice is dropped.
+    pub unsafe fn as_slice(&self) -> Result<&[T]> {
+        let len = self.len()?;
+        if len == 0 {
+            return Ok(&[]);
+        }
+        Ok(slice::from_raw_parts(self.data_ptr()?, len))
+    }
+
+    /// Constructs a mutable slice of all elements in column-major order
+    /// without copying.
+    ///
+    /// This function is unsafe for the same reasons as `as_slice`, and the
+    /// data also must not be read through any other handle while the slice
+    /// is alive.
+    pub unsafe fn as_mut_slice(&mut self) -> Result<&mut [T]> {
+        let len = self.len()?;
+        if len == 0 {
+            return Ok(&mut []);
+        }
+        Ok(slice::from_raw_parts_mut(self.data_ptr()?, len))
+    }
+
+    /// Converts a multi-dimensional index into an index into the column-major
+    /// data.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::DimensionMismatch if the number of indices differs from
+    /// the number of dimensions and Error::OutOfBounds if any index is out of
+    /// bounds.
+    pub fn linear_index(&self, idx: &[usize]) -> Result<usize> {
+        let dims = self.dims()?;
+        if idx.len() != dims.len() {
+            return Err(Error::DimensionMismatch);
+        }
+
+        let mut linear = 0;
+        let mut stride = 1;
+        for (&i, &dim) in idx.iter().zip(dims.iter()) {
+            if i >= dim {
+                return Err(Error::OutOfBounds);
+            }
+            linear += i * stride;
+            stride *= dim;
+        }
+        Ok(linear)
+    }
+
+    /// Returns the element at a multi-dimensional index.
+    pub fn get(&self, idx: &[usize]) -> Result<T> {
+        let i = self.linear_index(idx)?;
+        Ok(unsafe { *self.data_ptr()?.offset(i as isize) })
+    }
+
+    /// Sets the element at a multi-dimensional index.
+    pub fn set(&mut self, idx: &[usize], x: T) -> Result<()> {
+        let i = self.linear_index(idx)?;
+        unsafe {
+            *self.data_ptr()?.offset(i as isize) = x;
+        }
+        Ok(())
+    }
+
+    /// Constructs a Vec of all elements in column-major order.
+    pub fn to_vec(&self) -> Result<Vec<T>> {
+        // The slice is copied before anything else can touch the data.
+        unsafe { self.as_slice().map(|s| s.to_vec()) }
+    }
+}
+
+/// An Array borrowing its data from a Rust slice, created by
+/// `Array::borrow_slice`.
+///
+/// Only the `BorrowedArray` is bound to the lifetime of the slice, handles
+/// to the Array obtained through `Deref` are not.
+pub struct BorrowedArray<'a, T: 'a> {
+    array: Array,
+    _data: PhantomData<&'a mut [T]>,
+}
+
+impl<'a, T: 'a> Deref for BorrowedArray<'a, T> {
+    type Target = Array;
+    fn deref(&self) -> &Array {
+        &self.array
+    }
+}
+
 impl ByteArray {
     /// Returns the length of the ByteArray.
     pub fn len(&self) -> Result<usize> {
@@ -91,19 +737,43 @@ impl ByteArray {
         Ok(slice)
     }
 
+    /// Returns an iterator over the bytes of the ByteArray.
+    pub fn iter(&self) -> ByteArrayIter {
+        ByteArrayIter {
+            seq: self,
+            front: 0,
+            back: self.len().unwrap_or(0),
+        }
+    }
+
     /// Constructs a Vec of Values from the ByteArray.
     pub fn as_vec(&self) -> Result<Vec<u8>> {
         self.as_slice().map(|s| s.to_vec())
     }
 
     /// Returns the value at a specified index.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::OutOfBounds if `idx` is out of bounds, e.g. because
+    /// the ByteArray shrank while iterating over it.
     pub fn index(&self, idx: usize) -> Result<u8> {
+        if idx >= self.len()? {
+            return Err(Error::OutOfBounds);
+        }
         let byte = unsafe { jl_array_uint8_ref(self.lock()?, idx) };
         Ok(byte)
     }
 
     /// Sets the value at a specified index.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::OutOfBounds if `idx` is out of bounds.
     pub fn index_set(&self, idx: usize, x: u8) -> Result<()> {
+        if idx >= self.len()? {
+            return Err(Error::OutOfBounds);
+        }
         unsafe {
             jl_array_uint8_set(self.lock()?, idx, x);
         }
@@ -123,6 +793,38 @@ impl Svec {
         self.len().unwrap_or(0) == 0
     }
 
+    /// Returns an iterator over the elements of the Svec.
+    pub fn iter(&self) -> SvecIter {
+        SvecIter {
+            seq: self,
+            front: 0,
+            back: self.len().unwrap_or(0),
+        }
+    }
+
+    /// Constructs a new Svec from a sequence of Values. This is the fallible
+    /// counterpart of collecting into an Svec.
+    pub fn from_values<I>(values: I) -> Result<Svec>
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        Scope::enter(|scope| {
+            let mut rooted = vec![];
+            for x in values {
+                rooted.push(scope.root(x)?);
+            }
+
+            let raw = unsafe { jl_alloc_svec(rooted.len()) };
+            jl_catch!();
+            for (i, x) in rooted.iter().enumerate() {
+                unsafe {
+                    jl_svecset(raw, i, x.lock()?);
+                }
+            }
+            Svec::new(raw)
+        })
+    }
+
     /// Constructs a Vec of Values from the Svec.
     pub fn as_vec(&self) -> Result<Vec<Value>> {
         let len = self.len()?;
@@ -150,9 +852,108 @@ impl Svec {
     }
 }
 
+macro_rules! seq_iter {
+    { $( $(#[$attr:meta])* pub struct $iter:ident<'a>($seq:ident::$get:ident) -> $item:ty; )* } => {
+        $(
+            $(#[$attr])*
+            pub struct $iter<'a> {
+                seq: &'a $seq,
+                front: usize,
+                back: usize,
+            }
+
+            impl<'a> Iterator for $iter<'a> {
+                type Item = Result<$item>;
+
+                fn next(&mut self) -> Option<Result<$item>> {
+                    if self.front < self.back {
+                        self.front += 1;
+                        Some(self.seq.$get(self.front - 1))
+                    } else {
+                        None
+                    }
+                }
+
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let len = self.back - self.front;
+                    (len, Some(len))
+                }
+            }
+
+            impl<'a> DoubleEndedIterator for $iter<'a> {
+                fn next_back(&mut self) -> Option<Result<$item>> {
+                    if self.front < self.back {
+                        self.back -= 1;
+                        Some(self.seq.$get(self.back))
+                    } else {
+                        None
+                    }
+                }
+            }
+
+            impl<'a> ExactSizeIterator for $iter<'a> {}
+
+            impl<'a> IntoIterator for &'a $seq {
+                type Item = Result<$item>;
+                type IntoIter = $iter<'a>;
+
+                fn into_iter(self) -> $iter<'a> {
+                    self.iter()
+                }
+            }
+        )*
+    }
+}
+
+seq_iter! {
+    /// Borrowing iterator over the elements of an Array.
+    pub struct ArrayIter<'a>(Array::element) -> Value;
+    /// Borrowing iterator over the bytes of a ByteArray.
+    pub struct ByteArrayIter<'a>(ByteArray::index) -> u8;
+    /// Borrowing iterator over the elements of a Svec.
+    pub struct SvecIter<'a>(Svec::index) -> Value;
+}
+
+/// Collects Values into a new `Vector{Any}`.
+///
+/// ## Panics
+///
+/// Panics if Julia fails to allocate the Array. Use `Array::from_values` to
+/// handle the error instead.
+impl FromIterator<Value> for Array {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Array {
+        Array::from_values(iter).expect("couldn't allocate Array")
+    }
+}
+
+/// Collects Values into a new Svec.
+///
+/// ## Panics
+///
+/// Panics if Julia fails to allocate the Svec. Use `Svec::from_values` to
+/// handle the error instead.
+impl FromIterator<Value> for Svec {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Svec {
+        Svec::from_values(iter).expect("couldn't allocate Svec")
+    }
+}
+
 /// Creates a new Svec.
+///
+/// # Syntax
+///
+/// ```text
+/// jlvec![a, b, c]
+/// jlvec![x; n]
+/// jlvec![in iter]
+/// ```
 #[macro_export]
 macro_rules! jlvec {
+    [in $iter:expr] => {
+        $crate::api::Svec::from_values(
+            ::std::iter::IntoIterator::into_iter($iter).map($crate::api::Value::from)
+        )
+    };
     [] => {
         {
             use $crate::api::JlValue;
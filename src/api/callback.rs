@@ -0,0 +1,167 @@
+//! Module providing Rust closures callable from Julia code, either as new
+//! functions or as new methods of existing generic functions.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::rc::Rc;
+
+use sys::*;
+use error::{Result, Error};
+use api::{Julia, Value, JlValue, Array, Datatype, Function, Method, Module, Scope, Rooted};
+use api::exception::guard;
+
+/// Name of the private module holding the wrapper type for Rust closures.
+const CALLBACKS_MODULE: &str = "__JuliaRsCallbacks";
+
+/// Source of the private module holding the wrapper type for Rust closures.
+/// Calling a `RustFunction` collects the arguments into a `Vector{Any}` and
+/// passes it to `call` along with the id of the closure. `addmethod` defines
+/// a method of `f` taking one argument of each of `types` and forwarding
+/// them to `cb`.
+const CALLBACKS_JL: &str = "module __JuliaRsCallbacks
+struct RustFunction
+    call::Ptr{Void}
+    id::UInt
+end
+(f::RustFunction)(args...) = ccall(f.call, Any, (UInt, Any), f.id, Any[args...])
+
+function addmethod(f, types, cb)
+    args = [Symbol(:arg, i) for i in 1:length(types)]
+    params = [:($a::$T) for (a, T) in zip(args, types)]
+    @eval (::typeof($f))($(params...)) = $cb($(args...))
+    nothing
+end
+end";
+
+type Callback = Fn(&[Value]) -> Result<Value>;
+
+/// Closures registered in this thread.
+#[derive(Default)]
+struct Registry {
+    next_id: usize,
+    callbacks: HashMap<usize, Rc<Callback>>,
+    /// Ids of the closures by module and name.
+    names: HashMap<(usize, String), usize>,
+    /// Ids of the closures by generic function and method signature.
+    methods: HashMap<(usize, String), usize>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+/// Calls the closure registered as `id` with a `Vector{Any}` of arguments.
+extern "C" fn call_closure(id: usize, args: *mut jl_value_t) -> *mut jl_value_t {
+    guard(|| {
+        // The registry isn't borrowed during the call, so the closure can
+        // call back into other closures or even unregister itself.
+        let callback = REGISTRY.with(|registry| registry.borrow().callbacks.get(&id).cloned());
+        let callback = callback.ok_or(Error::CallError)?;
+
+        let args = Array::new(args as *mut jl_array_t)?;
+        let args = args.iter().collect::<Result<Vec<_>>>()?;
+        let ret = (*callback)(&args)?;
+        ret.lock()
+    })
+}
+
+pub(crate) fn helper(name: &str) -> Result<Function> {
+    Module::main_submodule(CALLBACKS_MODULE, CALLBACKS_JL)?.function(name)
+}
+
+/// Registers `f` under a new id.
+pub(crate) fn register<F>(f: F) -> usize
+where
+    F: Fn(&[Value]) -> Result<Value> + 'static,
+{
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry.callbacks.insert(id, Rc::new(f));
+        id
+    })
+}
+
+/// Drops the closure registered as `id`.
+pub(crate) fn unregister(id: usize) {
+    REGISTRY.with(|registry| registry.borrow_mut().callbacks.remove(&id));
+}
+
+/// Records that the closure registered as `id` is the body of `method` of
+/// `function`, dropping the closure of the method it replaced.
+pub(crate) fn register_method(function: &Function, method: &Method, id: usize) -> Result<()> {
+    let key = (function.lock()? as usize, method.sig()?.to_string());
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        if let Some(old) = registry.methods.insert(key, id) {
+            registry.callbacks.remove(&old);
+        }
+    });
+    Ok(())
+}
+
+/// Creates a new `RustFunction` calling the closure registered as `id`.
+pub(crate) fn rust_function<'s>(scope: &'s Scope, id: usize) -> Result<Rooted<'s, Value>> {
+    let callbacks = Module::main_submodule(CALLBACKS_MODULE, CALLBACKS_JL)?;
+    let rust_fn = Datatype::from_value(callbacks.global("RustFunction")?)?;
+
+    let call = call_closure as extern "C" fn(usize, *mut jl_value_t) -> *mut jl_value_t;
+    let call = Value::new(unsafe { jl_box_voidpointer(call as *mut c_void as *mut _) })?;
+    let call = scope.root(call)?;
+    let jl_id = scope.root(Value::from(id))?;
+
+    scope.root(rust_fn.new_struct(&[call.get().clone(), jl_id.get().clone()])?)
+}
+
+impl Julia {
+    /// Makes `f` callable from Julia code as `name` in `module`.
+    ///
+    /// The closure is kept alive until it's unregistered or replaced by
+    /// registering another closure under the same name. Errors returned by
+    /// `f` and panics are thrown as Julia exceptions.
+    pub fn register_fn<F>(&mut self, module: &Module, name: &str, f: F) -> Result<Function>
+    where
+        F: Fn(&[Value]) -> Result<Value> + 'static,
+    {
+        let key = (module.lock()? as usize, name.to_string());
+        let id = register(f);
+
+        let function = Scope::enter(|scope| {
+            let function = rust_function(scope, id)?;
+            module.set(name, &function)?;
+            Function::new(function.lock()? as *mut jl_function_t)
+        });
+
+        REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            if function.is_err() {
+                registry.callbacks.remove(&id);
+            } else if let Some(old) = registry.names.insert(key, id) {
+                registry.callbacks.remove(&old);
+            }
+        });
+
+        function
+    }
+
+    /// Drops the closure registered as `name` in `module` and binds `name` to
+    /// `nothing`. Returns whether a closure was registered.
+    ///
+    /// Julia code still holding on to the function gets an exception when
+    /// calling it.
+    pub fn unregister_fn(&mut self, module: &Module, name: &str) -> Result<bool> {
+        let key = (module.lock()? as usize, name.to_string());
+
+        let id = REGISTRY.with(|registry| registry.borrow_mut().names.remove(&key));
+        let id = match id {
+            Some(id) => id,
+            None => return Ok(false),
+        };
+
+        REGISTRY.with(|registry| registry.borrow_mut().callbacks.remove(&id));
+        module.set(name, &Value::nothing())?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,362 @@
+//! Module providing conversions between Rust collections and Julia values.
+//!
+//! `Vec<T>` corresponds to `Vector{T}`, `HashMap<K, V>` and `BTreeMap<K, V>`
+//! to `Dict{K,V}`, tuples of up to 12 elements to `Tuple`s and `Option<T>`
+//! to `Union{Void,T}`. The Julia element types are inferred from the Rust
+//! types through `JuliaType`, the elements are converted with `IntoJulia`
+//! and `FromJulia`.
+//!
+//! The `From` implementations panic if an element can't be converted or if
+//! Julia isn't initialized, use `IntoJulia::into_julia` to handle the error
+//! instead.
+
+use std::collections::{HashMap, BTreeMap};
+use std::convert::TryFrom;
+use std::hash::Hash;
+use std::iter::FromIterator;
+
+use sys::*;
+use error::{Result, Error};
+use api::{Julia, Value, JlValue, Array, Datatype, Function, Module, Svec, Scope, Rooted};
+use api::datatype::{Type, Tuple};
+use api::convert::{IntoJulia, FromJulia, JuliaType, field_at};
+
+/// Name of the private module holding the helpers for building and reading
+/// collections, shared with `dynamic` and `serde`.
+const COLLECTION_MODULE: &str = "__JuliaRsCollection";
+
+/// Source of the private module holding the helpers for building and reading
+/// collections. Elements are always collected into a `Vector{Any}` first.
+/// `vector` and `dict` convert them to the given element types, or narrow
+/// them to the types of the elements if none are given.
+const COLLECTION_JL: &str = "module __JuliaRsCollection
+const DictType = isdefined(Base, :AbstractDict) ? Base.AbstractDict : Base.Associative
+
+vector(xs) = isempty(xs) ? xs : map(identity, xs)
+vector(T, xs) = convert(Vector{T}, xs)
+totuple(xs) = (xs...,)
+dict(ks, vs) = Dict(k => v for (k, v) in zip(ks, vs))
+dict(K, V, ks, vs) = Dict{K,V}(zip(ks, vs))
+
+if isdefined(Base, :NamedTuple)
+    namedtuple(ks, vs) = NamedTuple{(Symbol.(ks)...,)}((vs...,))
+else
+    # NamedTuples were only added in Julia 0.7.
+    namedtuple(ks, vs) = dict(ks, vs)
+end
+
+isdict(x) = isa(x, DictType)
+dictkeys(d) = Any[k for k in keys(d)]
+dictvalues(d) = Any[v for v in values(d)]
+
+hasfields(x) = nfields(x) > 0
+structkeys(x) = Any[String(f) for f in fieldnames(typeof(x))]
+structvalues(x) = Any[getfield(x, f) for f in fieldnames(typeof(x))]
+typestring(t) = string(t)
+end";
+
+pub(crate) fn helper(name: &str) -> Result<Function> {
+    Module::main_submodule(COLLECTION_MODULE, COLLECTION_JL)?.function(name)
+}
+
+/// The keys and values of a `Dict` or struct.
+pub(crate) type Entries<'s> = (Rooted<'s, Array>, Rooted<'s, Array>);
+
+/// Returns the keys and values of a dict as two rooted `Vector{Any}`s, or
+/// None if `value` isn't an `AbstractDict`.
+///
+/// Both arrays are `Vector{Any}`s, so their elements are rooted for as long
+/// as the arrays are.
+pub(crate) fn dict_entries<'s>(scope: &'s Scope, value: &Value) -> Result<Option<Entries<'s>>> {
+    if !bool::try_from(&helper("isdict")?.call(&[value.clone()])?)? {
+        return Ok(None);
+    }
+
+    let keys = scope.root(Array::from_value(helper("dictkeys")?.call(&[value.clone()])?)?)?;
+    let values = scope.root(Array::from_value(helper("dictvalues")?.call(&[value.clone()])?)?)?;
+    Ok(Some((keys, values)))
+}
+
+/// Returns the Julia type of `T`. `IntoJulia` has no `Julia` handle to pass
+/// on, so one is only made up after checking that Julia is initialized.
+///
+/// ## Errors
+///
+/// Returns Error::JuliaNotInitialized if Julia isn't initialized.
+fn julia_type<T: JuliaType>() -> Result<Type> {
+    if !Julia::is_initialized() {
+        return Err(Error::JuliaNotInitialized);
+    }
+    let jl = unsafe { Julia::new_unchecked() };
+    T::julia_type(&jl)
+}
+
+/// Converts `values` into a new, rooted `Vector{Any}`.
+fn vector_any<'s, T, I>(scope: &'s Scope, values: I) -> Result<Rooted<'s, Value>>
+where
+    T: IntoJulia,
+    I: IntoIterator<Item = T>,
+{
+    let mut rooted = vec![];
+    for value in values {
+        rooted.push(scope.root(value.into_julia()?)?.get().clone());
+    }
+    scope.root(Array::from_values(rooted)?.to_value()?)
+}
+
+/// Converts the entries of a Rust map into a new `Dict{K,V}`.
+fn new_dict<K, V, I>(entries: I) -> Result<Value>
+where
+    K: IntoJulia + JuliaType,
+    V: IntoJulia + JuliaType,
+    I: IntoIterator<Item = (K, V)>,
+{
+    let (keys, values): (Vec<K>, Vec<V>) = entries.into_iter().unzip();
+
+    Scope::enter(|scope| {
+        // Types like unions aren't cached by Julia, so they're rooted too.
+        let ktype = scope.root(julia_type::<K>()?.to_value()?)?;
+        let vtype = scope.root(julia_type::<V>()?.to_value()?)?;
+        let keys = vector_any(scope, keys)?;
+        let values = vector_any(scope, values)?;
+        let args = [ktype, vtype, keys, values];
+        helper("dict")?.call(args.iter().map(|arg| arg.get()))
+    })
+}
+
+/// Reads the entries of a Julia `Dict` into a Rust map.
+///
+/// ## Errors
+///
+/// Returns Error::TypeMismatch if `value` isn't an `AbstractDict`.
+fn read_dict<K, V, C>(value: &Value) -> Result<C>
+where
+    K: FromJulia,
+    V: FromJulia,
+    C: FromIterator<(K, V)>,
+{
+    Scope::enter(|scope| {
+        let (keys, values) = dict_entries(scope, value)?.ok_or(Error::TypeMismatch)?;
+        (0..keys.len()?)
+            .map(|idx| {
+                let key = K::from_julia(&keys.element(idx)?)?;
+                let entry = V::from_julia(&values.element(idx)?)?;
+                Ok((key, entry))
+            })
+            .collect()
+    })
+}
+
+impl<T: JuliaType> JuliaType for Vec<T> {
+    fn julia_type(jl: &Julia) -> Result<Type> {
+        Scope::enter(|scope| scope.root(T::julia_type(jl)?)?.array_type(1)?.to_type())
+    }
+}
+
+impl<T: IntoJulia + JuliaType> IntoJulia for Vec<T> {
+    fn into_julia(self) -> Result<Value> {
+        Scope::enter(|scope| {
+            let eltype = scope.root(julia_type::<T>()?.to_value()?)?;
+            let values = vector_any(scope, self)?;
+            helper("vector")?.call(&[eltype.get().clone(), values.get().clone()])
+        })
+    }
+}
+
+impl<T: FromJulia> FromJulia for Vec<T> {
+    fn from_julia(value: &Value) -> Result<Vec<T>> {
+        if !value.is_array() {
+            return Err(Error::TypeMismatch);
+        }
+        let array = Array::new(value.lock()? as *mut jl_array_t)?;
+        if array.ndims()? != 1 {
+            return Err(Error::DimensionMismatch);
+        }
+
+        Scope::enter(|scope| {
+            let mut elements = Vec::with_capacity(array.len()?);
+            for idx in 0..array.len()? {
+                elements.push(T::from_julia(scope.root(array.element(idx)?)?.get())?);
+            }
+            Ok(elements)
+        })
+    }
+}
+
+impl<T: JuliaType> JuliaType for Option<T> {
+    fn julia_type(jl: &Julia) -> Result<Type> {
+        Scope::enter(|scope| {
+            let some = scope.root(T::julia_type(jl)?)?;
+            let mut types = [Datatype::void().lock()? as *mut jl_value_t, some.lock()?];
+            let raw = unsafe { jl_type_union(types.as_mut_ptr(), types.len()) };
+            jl_catch!();
+            Type::new(raw)
+        })
+    }
+}
+
+impl<T: IntoJulia> IntoJulia for Option<T> {
+    fn into_julia(self) -> Result<Value> {
+        match self {
+            Some(value) => value.into_julia(),
+            None => Ok(Value::nothing()),
+        }
+    }
+}
+
+impl<T: FromJulia> FromJulia for Option<T> {
+    fn from_julia(value: &Value) -> Result<Option<T>> {
+        if value.is_nothing() {
+            Ok(None)
+        } else {
+            T::from_julia(value).map(Some)
+        }
+    }
+}
+
+macro_rules! convert_map {
+    ($map:ident, $($kbound:tt)+) => {
+        impl<K: JuliaType + $($kbound)+, V: JuliaType> JuliaType for $map<K, V> {
+            fn julia_type(jl: &Julia) -> Result<Type> {
+                Scope::enter(|scope| {
+                    let ktype = scope.root(K::julia_type(jl)?.to_value()?)?;
+                    let vtype = scope.root(V::julia_type(jl)?.to_value()?)?;
+                    Datatype::dict()?.to_type()?.apply_type2(&ktype, &vtype)
+                })
+            }
+        }
+
+        impl<K, V> IntoJulia for $map<K, V>
+        where
+            K: IntoJulia + JuliaType + $($kbound)+,
+            V: IntoJulia + JuliaType,
+        {
+            fn into_julia(self) -> Result<Value> {
+                new_dict(self)
+            }
+        }
+
+        impl<K: FromJulia + $($kbound)+, V: FromJulia> FromJulia for $map<K, V> {
+            fn from_julia(value: &Value) -> Result<$map<K, V>> {
+                read_dict(value)
+            }
+        }
+
+        impl<K, V> From<$map<K, V>> for Value
+        where
+            K: IntoJulia + JuliaType + $($kbound)+,
+            V: IntoJulia + JuliaType,
+        {
+            fn from(map: $map<K, V>) -> Value {
+                map.into_julia().expect("cannot convert a map into a Julia Dict")
+            }
+        }
+
+        impl<'a, K: FromJulia + $($kbound)+, V: FromJulia> TryFrom<&'a Value> for $map<K, V> {
+            type Error = Error;
+            fn try_from(value: &Value) -> Result<$map<K, V>> {
+                $map::from_julia(value)
+            }
+        }
+    };
+}
+
+convert_map!(HashMap, Eq + Hash);
+convert_map!(BTreeMap, Ord);
+
+impl<T: IntoJulia + JuliaType> From<Vec<T>> for Value {
+    fn from(vec: Vec<T>) -> Value {
+        vec.into_julia().expect("cannot convert a Vec into a Julia Vector")
+    }
+}
+
+impl<'a, T: FromJulia> TryFrom<&'a Value> for Vec<T> {
+    type Error = Error;
+    fn try_from(value: &Value) -> Result<Vec<T>> {
+        Vec::from_julia(value)
+    }
+}
+
+impl<T: IntoJulia> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Value {
+        option.into_julia().expect("cannot convert an Option into a Julia value")
+    }
+}
+
+impl<'a, T: FromJulia> TryFrom<&'a Value> for Option<T> {
+    type Error = Error;
+    fn try_from(value: &Value) -> Result<Option<T>> {
+        Option::from_julia(value)
+    }
+}
+
+macro_rules! convert_tuple {
+    ($($len:expr => ($($t:ident $idx:tt),+)),*) => {
+        $(
+            impl<$($t: JuliaType),+> JuliaType for ($($t,)+) {
+                fn julia_type(jl: &Julia) -> Result<Type> {
+                    Scope::enter(|scope| {
+                        let params = vec![
+                            $( scope.root($t::julia_type(jl)?.to_value()?)?.get().clone() ),+
+                        ];
+                        let params = scope.root(Svec::from_values(params)?)?;
+                        Tuple::apply(&params)?.into_value()
+                    })
+                }
+            }
+
+            impl<$($t: IntoJulia),+> IntoJulia for ($($t,)+) {
+                fn into_julia(self) -> Result<Value> {
+                    Scope::enter(|scope| {
+                        // Each element is rooted as soon as it's converted.
+                        let values = vec![
+                            $( scope.root(self.$idx.into_julia()?)?.get().clone() ),+
+                        ];
+                        let values = scope.root(Array::from_values(values)?.to_value()?)?;
+                        helper("totuple")?.call(&[values.get().clone()])
+                    })
+                }
+            }
+
+            impl<$($t: FromJulia),+> FromJulia for ($($t,)+) {
+                fn from_julia(value: &Value) -> Result<($($t,)+)> {
+                    if !value.is_tuple() || value.datatype()?.nfields()? != $len {
+                        return Err(Error::TypeMismatch);
+                    }
+
+                    Scope::enter(|scope| {
+                        Ok(($( $t::from_julia(scope.root(field_at(value, $idx)?)?.get())?, )+))
+                    })
+                }
+            }
+
+            impl<$($t: IntoJulia),+> From<($($t,)+)> for Value {
+                fn from(tuple: ($($t,)+)) -> Value {
+                    tuple.into_julia().expect("cannot convert a tuple into a Julia Tuple")
+                }
+            }
+
+            impl<'a, $($t: FromJulia),+> TryFrom<&'a Value> for ($($t,)+) {
+                type Error = Error;
+                fn try_from(value: &Value) -> Result<($($t,)+)> {
+                    <($($t,)+)>::from_julia(value)
+                }
+            }
+        )*
+    };
+}
+
+convert_tuple! {
+    1 => (A 0),
+    2 => (A 0, B 1),
+    3 => (A 0, B 1, C 2),
+    4 => (A 0, B 1, C 2, D 3),
+    5 => (A 0, B 1, C 2, D 3, E 4),
+    6 => (A 0, B 1, C 2, D 3, E 4, F 5),
+    7 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6),
+    8 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7),
+    9 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8),
+    10 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9),
+    11 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10),
+    12 => (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11)
+}
@@ -0,0 +1,416 @@
+//! Module providing traits for converting Rust values to and from Julia
+//! values.
+//!
+//! The traits are implemented for the boxable primitives, `Complex`,
+//! `Rational`, `String` and `Value`, and for collections of those in
+//! `api::collection`. julia-derive provides
+//! `#[derive(IntoJulia, FromJulia)]`, which maps the fields of a Rust struct
+//! by name onto the fields of a Julia struct, and `#[derive(JuliaType)]`,
+//! which defines that Julia struct from the Rust one. The hidden functions
+//! in this module are used by the generated code.
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use sys::*;
+use error::{Result, Error};
+use api::{Julia, Value, JlValue, Datatype, IsBits, Module, Svec, Scope, Symbol, IntoSymbol};
+use api::datatype::{Type, TypeBuilder};
+use api::primitive::{Number, Real, Integer, Complex, Rational};
+
+thread_local! {
+    /// Julia types defined through `JuliaType` by the `TypeId` of the Rust
+    /// type.
+    static TYPES: RefCell<HashMap<TypeId, Datatype>> = RefCell::new(HashMap::new());
+}
+
+/// Trait implemented by types which can be converted into a Julia value.
+pub trait IntoJulia {
+    /// Converts self into a new Julia value.
+    fn into_julia(self) -> Result<Value>;
+}
+
+/// Trait implemented by types which can be constructed from a Julia value.
+pub trait FromJulia: Sized {
+    /// Constructs a Rust value from `value`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::TypeMismatch or Error::InvalidUnbox if `value` has the
+    /// wrong type and Error::FieldMismatch if a field doesn't match.
+    fn from_julia(value: &Value) -> Result<Self>;
+}
+
+/// Trait implemented by Rust types with a corresponding Julia type.
+pub trait JuliaType: 'static {
+    /// Returns the Julia type of `Self`, defining it on first use.
+    ///
+    /// This isn't necessarily a Datatype, e.g. `Option<T>` corresponds to
+    /// `Union{Void,T}`.
+    fn julia_type(jl: &Julia) -> Result<Type>;
+}
+
+macro_rules! convert_simple {
+    ($($t:ty),*) => {
+        $(
+            impl IntoJulia for $t {
+                fn into_julia(self) -> Result<Value> {
+                    Ok(Value::from(self))
+                }
+            }
+
+            impl FromJulia for $t {
+                fn from_julia(value: &Value) -> Result<$t> {
+                    <$t>::try_from(value)
+                }
+            }
+        )*
+    };
+}
+
+convert_simple!(bool, char, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, String);
+
+macro_rules! julia_type_isbits {
+    ($($t:ty),*) => {
+        $(
+            impl JuliaType for $t {
+                fn julia_type(_: &Julia) -> Result<Type> {
+                    <$t as IsBits>::datatype()?.to_type()
+                }
+            }
+        )*
+    };
+}
+
+julia_type_isbits!(bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl JuliaType for char {
+    fn julia_type(_: &Julia) -> Result<Type> {
+        Datatype::char().to_type()
+    }
+}
+
+impl JuliaType for String {
+    fn julia_type(_: &Julia) -> Result<Type> {
+        Datatype::string().to_type()
+    }
+}
+
+impl JuliaType for Value {
+    fn julia_type(_: &Julia) -> Result<Type> {
+        Datatype::any().to_type()
+    }
+}
+
+impl<T: IsBits + Number + Real + 'static> JuliaType for Complex<T> {
+    fn julia_type(_: &Julia) -> Result<Type> {
+        <Complex<T> as IsBits>::datatype()?.to_type()
+    }
+}
+
+impl<T: IsBits + Number + Real> IntoJulia for Complex<T> {
+    fn into_julia(self) -> Result<Value> {
+        Value::from_bits(self)
+    }
+}
+
+impl<T: IsBits + Number + Real> FromJulia for Complex<T> {
+    fn from_julia(value: &Value) -> Result<Complex<T>> {
+        Complex::try_from(value)
+    }
+}
+
+impl<T: IsBits + Number + Real + Integer + 'static> JuliaType for Rational<T> {
+    fn julia_type(_: &Julia) -> Result<Type> {
+        <Rational<T> as IsBits>::datatype()?.to_type()
+    }
+}
+
+impl<T: IsBits + Number + Real + Integer> IntoJulia for Rational<T> {
+    fn into_julia(self) -> Result<Value> {
+        Value::from_bits(self)
+    }
+}
+
+impl<T: IsBits + Number + Real + Integer> FromJulia for Rational<T> {
+    fn from_julia(value: &Value) -> Result<Rational<T>> {
+        Rational::try_from(value)
+    }
+}
+
+impl<'a> IntoJulia for &'a str {
+    fn into_julia(self) -> Result<Value> {
+        Ok(Value::from(self))
+    }
+}
+
+impl IntoJulia for Value {
+    fn into_julia(self) -> Result<Value> {
+        Ok(self)
+    }
+}
+
+impl FromJulia for Value {
+    fn from_julia(value: &Value) -> Result<Value> {
+        Ok(value.clone())
+    }
+}
+
+/// Splits a path relative to `Main`, e.g. `"Base.Complex"`, into the module
+/// it refers to and the last name.
+fn resolve_path(path: &str) -> Result<(Module, &str)> {
+    let mut module = unsafe { Module::new_unchecked(jl_main_module) };
+    let mut parts = path.split('.');
+    let name = parts.next_back().ok_or(Error::InvalidSymbol)?;
+
+    for part in parts {
+        let global = module.global(part)?;
+        if !global.is_module() {
+            return Err(Error::TypeMismatch);
+        }
+        module = Module::from_value(global)?;
+    }
+
+    Ok((module, name))
+}
+
+/// Looks up a Julia type by its path relative to `Main`, e.g. `"Point"` or
+/// `"Base.Complex"`.
+#[doc(hidden)]
+pub fn lookup_datatype(path: &str) -> Result<Datatype> {
+    let (module, name) = resolve_path(path)?;
+    let global = module.global(name)?;
+    if !global.is_datatype() {
+        return Err(Error::TypeMismatch);
+    }
+    Datatype::from_value(global)
+}
+
+/// Returns the Julia type cached for `T`, calling `define` if there's none.
+#[doc(hidden)]
+pub fn cached_type<T, F>(define: F) -> Result<Datatype>
+where
+    T: 'static,
+    F: FnOnce() -> Result<Datatype>,
+{
+    let id = TypeId::of::<T>();
+    if let Some(datatype) = TYPES.with(|types| types.borrow().get(&id).cloned()) {
+        return Ok(datatype);
+    }
+
+    let datatype = define()?;
+    TYPES.with(|types| types.borrow_mut().insert(id, datatype.clone()));
+    Ok(datatype)
+}
+
+/// Checks that an existing Julia struct has exactly the given fields.
+fn check_fields(datatype: &Datatype, fields: &[(&str, Type)]) -> Result<()> {
+    let names = datatype.field_names()?;
+    let types = datatype.field_types()?;
+
+    if let Some(name) = names.get(fields.len()) {
+        return Err(Error::FieldMismatch(name.clone()));
+    }
+    for (i, &(name, ref ty)) in fields.iter().enumerate() {
+        if i >= names.len() || names[i] != name {
+            return Err(Error::FieldMismatch(name.to_string()));
+        }
+
+        // Unions aren't interned, so types are compared by structure.
+        let equal = unsafe { jl_types_equal(types.index(i)?.lock()?, ty.lock()?) };
+        jl_catch!();
+        if equal == 0 {
+            return Err(Error::FieldMismatch(name.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Defines a new Julia struct under `path`, relative to `Main`.
+///
+/// If `path` is already bound to a struct with the same fields, that struct
+/// is returned instead.
+///
+/// ## Errors
+///
+/// Returns Error::FieldMismatch if `path` is bound to a struct with other
+/// fields and Error::TypeMismatch if it isn't bound to a Datatype.
+#[doc(hidden)]
+pub fn define_struct(path: &str, mutable: bool, fields: &[(&str, Type)]) -> Result<Datatype> {
+    let (module, name) = resolve_path(path)?;
+    if let Ok(global) = module.global(name) {
+        if !global.is_datatype() {
+            return Err(Error::TypeMismatch);
+        }
+        let datatype = Datatype::from_value(global)?;
+        check_fields(&datatype, fields)?;
+        return Ok(datatype);
+    }
+
+    Scope::enter(|scope| {
+        let mut fnames = vec![];
+        let mut ftypes = vec![];
+        for &(fname, ref ftype) in fields {
+            fnames.push(Value::from_value(fname.into_symbol()?)?);
+            ftypes.push(ftype.to_value()?);
+        }
+        let fnames = scope.root(Svec::from_values(fnames)?)?;
+        let ftypes = scope.root(Svec::from_values(ftypes)?)?;
+
+        let datatype = TypeBuilder::new()
+            .name(name)
+            .fnames(&fnames)
+            .ftypes(&ftypes)
+            .mutable(mutable)
+            .build()?;
+        let datatype = scope.root(datatype)?;
+
+        // Binding the type also keeps it alive.
+        module.set_const(name, &datatype.to_value()?)?;
+        Ok(datatype.get().clone())
+    })
+}
+
+/// Checks that `value` can be stored in the field `name` of type `ty`.
+fn check_field(value: &Value, ty: &Value, name: &str) -> Result<()> {
+    let isa = unsafe { jl_isa(value.lock()?, ty.lock()?) };
+    jl_catch!();
+
+    if isa == 0 {
+        return Err(Error::FieldMismatch(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Creates a new instance of `datatype` with the fields given by name.
+///
+/// ## Errors
+///
+/// Returns Error::FieldMismatch if a field is missing, unknown, or if a value
+/// doesn't have the type of its field.
+#[doc(hidden)]
+pub fn new_struct_by_name(datatype: &Datatype, fields: &[(&str, Value)]) -> Result<Value> {
+    let names = datatype.field_names()?;
+    let types = datatype.field_types()?;
+
+    let unknown = fields.iter().find(|field| !names.iter().any(|name| name == field.0));
+    if let Some(&(name, _)) = unknown {
+        return Err(Error::FieldMismatch(name.to_string()));
+    }
+
+    let mut values = vec![];
+    for (i, name) in names.iter().enumerate() {
+        let value = fields.iter().find(|field| field.0 == name).map(|field| &field.1);
+        let value = value.ok_or_else(|| Error::FieldMismatch(name.clone()))?;
+        check_field(value, &types.index(i)?, name)?;
+        values.push(value);
+    }
+
+    datatype.new_struct(values)
+}
+
+/// Creates a new instance of `datatype` with the fields given in declaration
+/// order.
+///
+/// ## Errors
+///
+/// Returns Error::FieldMismatch if the number of fields doesn't match or if a
+/// value doesn't have the type of its field.
+#[doc(hidden)]
+pub fn new_struct_checked(datatype: &Datatype, values: &[Value]) -> Result<Value> {
+    let names = datatype.field_names()?;
+    let types = datatype.field_types()?;
+
+    if values.len() != names.len() {
+        let name = names
+            .get(values.len())
+            .cloned()
+            .unwrap_or_else(|| values.len().to_string());
+        return Err(Error::FieldMismatch(name));
+    }
+
+    for (i, (value, name)) in values.iter().zip(&names).enumerate() {
+        check_field(value, &types.index(i)?, name)?;
+    }
+
+    datatype.new_struct(values)
+}
+
+/// Checks that `value` is an instance of `datatype` with exactly the fields
+/// `names`.
+///
+/// ## Errors
+///
+/// Returns Error::TypeMismatch if `value` isn't a `datatype` and
+/// Error::FieldMismatch if the fields don't match.
+#[doc(hidden)]
+pub fn check_struct(value: &Value, datatype: &Datatype, names: &[&str]) -> Result<()> {
+    if !value.isa(datatype)? {
+        return Err(Error::TypeMismatch);
+    }
+
+    let fields = datatype.field_names()?;
+    if let Some(name) = names.iter().find(|name| !fields.iter().any(|field| field == *name)) {
+        return Err(Error::FieldMismatch(name.to_string()));
+    }
+    if let Some(field) = fields.iter().find(|field| !names.contains(&field.as_str())) {
+        return Err(Error::FieldMismatch(field.clone()));
+    }
+    Ok(())
+}
+
+/// Checks that `value` is an instance of `datatype` with `nfields` fields.
+///
+/// ## Errors
+///
+/// Returns Error::TypeMismatch if `value` isn't a `datatype` and
+/// Error::FieldMismatch if the number of fields doesn't match.
+#[doc(hidden)]
+pub fn check_tuple_struct(value: &Value, datatype: &Datatype, nfields: usize) -> Result<()> {
+    if !value.isa(datatype)? {
+        return Err(Error::TypeMismatch);
+    }
+
+    let fields = datatype.field_names()?;
+    if fields.len() != nfields {
+        let name = fields.get(nfields).cloned().unwrap_or_else(|| nfields.to_string());
+        return Err(Error::FieldMismatch(name));
+    }
+    Ok(())
+}
+
+/// Returns the field `name` of `value`.
+#[doc(hidden)]
+pub fn field(value: &Value, name: &str) -> Result<Value> {
+    value.get(name).map_err(|err| match err {
+        Error::InvalidSymbol => Error::FieldMismatch(name.to_string()),
+        err => err,
+    })
+}
+
+/// Returns the field at `idx` of `value`.
+#[doc(hidden)]
+pub fn field_at(value: &Value, idx: usize) -> Result<Value> {
+    let raw = unsafe { jl_fieldref(value.lock()?, idx) };
+    jl_catch!();
+    Value::new(raw)
+}
+
+/// Returns a new Julia symbol as a Value.
+#[doc(hidden)]
+pub fn symbol(name: &str) -> Result<Value> {
+    Value::from_value(name.into_symbol()?)
+}
+
+/// Returns the name of `value` if it's a symbol.
+#[doc(hidden)]
+pub fn symbol_name(value: &Value) -> Result<Option<String>> {
+    if !value.is_symbol() {
+        return Ok(None);
+    }
+
+    let symbol = Symbol::new(value.lock()? as *mut jl_sym_t)?;
+    String::try_from(&symbol).map(Some)
+}
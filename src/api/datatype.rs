@@ -1,13 +1,15 @@
 
 //! Module providing wrappers for the native Julia type-types.
 
+use std::mem;
 use std::ptr;
 use std::result;
 use std::convert::TryFrom;
 
 use sys::*;
 use error::{Result, Error};
-use api::{Value, JlValue, IntoSymbol, Array, Svec};
+use api::{Value, JlValue, IntoSymbol, Symbol, Array, Svec, Module};
+use api::primitive::{Number, Real, Integer, Complex, Rational};
 
 #[derive(Clone, Copy, Hash, PartialEq, Debug)]
 pub enum VarargKind {
@@ -99,6 +101,15 @@ impl Type {
         Type::new(raw)
     }
 
+    /// Returns the type of an Array with this element type and `ndims`
+    /// dimensions, i.e. `Array{Self, ndims}`.
+    pub fn array_type(&self, ndims: usize) -> Result<Datatype> {
+        let eltype = self.lock()?;
+        let raw = unsafe { jl_apply_array_type(eltype, ndims) };
+        jl_catch!();
+        Datatype::new(raw as *mut _)
+    }
+
     /// Applies function to the inner pointer.
     pub fn map<T, F>(&self, f: F) -> Result<T>
     where
@@ -234,6 +245,102 @@ impl Datatype {
         Value::new(value)
     }
 
+    /// Returns the number of fields.
+    pub fn nfields(&self) -> Result<usize> {
+        self.field_types()?.len()
+    }
+
+    /// Returns the names of the fields in declaration order.
+    pub fn field_names(&self) -> Result<Vec<String>> {
+        let dt = self.lock()?;
+        let names = Svec::new(unsafe { (*(*dt).name).names })?;
+        names
+            .iter()
+            .map(|name| String::try_from(&Symbol::from_value(name?)?))
+            .collect()
+    }
+
+    /// Returns the types of the fields in declaration order.
+    pub fn field_types(&self) -> Result<Svec> {
+        let dt = self.lock()?;
+        Svec::new(unsafe { (*dt).types })
+    }
+
+    /// Checks if instances of this type are stored inline, without any
+    /// pointers.
+    pub fn is_bits(&self) -> bool {
+        self.map_or(|v| unsafe { jl_isbits(v) }, false)
+    }
+
+    /// Returns the memory layout of an isbits type.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::TypeMismatch if the type isn't isbits.
+    pub fn layout(&self) -> Result<Layout> {
+        if !self.is_bits() {
+            return Err(Error::TypeMismatch);
+        }
+
+        let dt = self.lock()?;
+        let layout = unsafe {
+            Layout {
+                size: jl_datatype_size(dt),
+                align: jl_datatype_align(dt),
+                fields: (0..jl_datatype_nfields(dt))
+                    .map(|i| {
+                        FieldLayout {
+                            offset: jl_field_offset(dt, i),
+                            size: jl_field_size(dt, i),
+                        }
+                    })
+                    .collect(),
+                field_types: vec![],
+            }
+        };
+        Ok(layout)
+    }
+
+    /// Compares the layout of this isbits type with the layout of a Rust
+    /// type, usually obtained with `jl_layout!`, and returns every
+    /// difference. Besides offsets and sizes, the types of the fields are
+    /// compared if the Rust layout lists them. An empty Vec means that values
+    /// can be reinterpreted safely.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::TypeMismatch if the type isn't isbits.
+    pub fn check_layout(&self, layout: &Layout) -> Result<Vec<LayoutMismatch>> {
+        let mut mismatches = self.layout()?.mismatches(layout);
+
+        let julia_types = self.field_types()?;
+        let nfields = julia_types.len()?.min(layout.field_types.len());
+        for index in 0..nfields {
+            let julia = julia_types.index(index)?;
+            let rust = (layout.field_types[index])()?;
+            if julia.lock()? as usize != rust.lock()? as usize {
+                mismatches.push(LayoutMismatch::FieldType {
+                    index: index,
+                    julia: julia.to_string(),
+                    rust: rust.to_string(),
+                });
+            }
+        }
+
+        Ok(mismatches)
+    }
+
+    /// Returns the type of an Array with this element type and `ndims`
+    /// dimensions, i.e. `Array{Self, ndims}`.
+    pub fn array_type(&self, ndims: usize) -> Result<Datatype> {
+        self.to_type()?.array_type(ndims)
+    }
+
+    /// Returns a new Type handle to this Datatype.
+    pub fn to_type(&self) -> Result<Type> {
+        Type::new(self.lock()? as *mut jl_value_t)
+    }
+
     pub fn any() -> Datatype {
         unsafe { Datatype::new_unchecked(jl_any_type) }
     }
@@ -285,12 +392,25 @@ impl Datatype {
     pub fn float64() -> Datatype {
         unsafe { Datatype::new_unchecked(jl_float64_type) }
     }
+    pub fn string() -> Datatype {
+        unsafe { Datatype::new_unchecked(jl_string_type) }
+    }
     pub fn void() -> Datatype {
         unsafe { Datatype::new_unchecked(jl_void_type) }
     }
     pub fn complex() -> Datatype {
         unsafe { Datatype::new_unchecked(jl_complex_type as *mut _) }
     }
+    /// Returns `Base.Rational`, which has no global in the C API.
+    pub fn rational() -> Result<Datatype> {
+        let base = unsafe { Module::new_unchecked(jl_base_module) };
+        Datatype::from_value(base.global("Rational")?)
+    }
+    /// Returns `Base.Dict`, which has no global in the C API.
+    pub fn dict() -> Result<Datatype> {
+        let base = unsafe { Module::new_unchecked(jl_base_module) };
+        Datatype::from_value(base.global("Dict")?)
+    }
     pub fn void_pointer() -> Datatype {
         unsafe { Datatype::new_unchecked(jl_voidpointer_type) }
     }
@@ -305,6 +425,219 @@ impl Default for Datatype {
     }
 }
 
+/// Offset and size of a field, in bytes.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct FieldLayout {
+    pub offset: usize,
+    pub size: usize,
+}
+
+/// Memory layout of an isbits type or a `#[repr(C)]` Rust type.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub size: usize,
+    pub align: usize,
+    pub fields: Vec<FieldLayout>,
+    /// Functions returning the Julia types of the fields of a Rust type.
+    /// Empty for the layouts of Julia types.
+    pub field_types: Vec<fn() -> Result<Datatype>>,
+}
+
+/// A difference between a Julia layout and a Rust layout.
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+pub enum LayoutMismatch {
+    /// The sizes of the types differ.
+    Size { julia: usize, rust: usize },
+    /// The alignments of the types differ.
+    Align { julia: usize, rust: usize },
+    /// The numbers of fields differ.
+    FieldCount { julia: usize, rust: usize },
+    /// The field at `index` differs in its offset or size.
+    Field {
+        index: usize,
+        julia: FieldLayout,
+        rust: FieldLayout,
+    },
+    /// The field at `index` has a different type, e.g. `Int64` instead of
+    /// `Float64`.
+    FieldType {
+        index: usize,
+        julia: String,
+        rust: String,
+    },
+}
+
+impl Layout {
+    /// Returns every difference in size, alignment and field offsets between
+    /// this layout, of a Julia type, and the layout of a Rust type. Field
+    /// types are only compared by `Datatype::check_layout`.
+    pub fn mismatches(&self, rust: &Layout) -> Vec<LayoutMismatch> {
+        let mut mismatches = vec![];
+
+        if self.size != rust.size {
+            mismatches.push(LayoutMismatch::Size {
+                julia: self.size,
+                rust: rust.size,
+            });
+        }
+        if self.align != rust.align {
+            mismatches.push(LayoutMismatch::Align {
+                julia: self.align,
+                rust: rust.align,
+            });
+        }
+        if self.fields.len() != rust.fields.len() {
+            mismatches.push(LayoutMismatch::FieldCount {
+                julia: self.fields.len(),
+                rust: rust.fields.len(),
+            });
+        }
+
+        let fields = self.fields.iter().zip(&rust.fields).enumerate();
+        for (index, (julia, rust)) in fields {
+            if julia != rust {
+                mismatches.push(LayoutMismatch::Field {
+                    index: index,
+                    julia: *julia,
+                    rust: *rust,
+                });
+            }
+        }
+
+        mismatches
+    }
+}
+
+#[doc(hidden)]
+pub fn size_of_pointee<T>(_: *const T) -> usize {
+    mem::size_of::<T>()
+}
+
+#[doc(hidden)]
+pub fn field_datatype<T: IsBits>(_: *const T) -> fn() -> Result<Datatype> {
+    T::datatype
+}
+
+/// Returns the `Layout` of a `#[repr(C)]` struct. The fields have to be
+/// listed in declaration order and their types have to implement `IsBits`.
+///
+/// ## Example
+///
+/// ```
+/// # #[macro_use] extern crate julia;
+/// #[repr(C)]
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+///
+/// # fn main() {
+/// let layout = jl_layout!(Point { x, y });
+/// assert_eq!(layout.fields[1].offset, 8);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! jl_layout {
+    ($t:ty { $( $field:ident ),* $(,)* }) => {
+        {
+            // The fields of a zeroed value are only addressed to compute
+            // their offsets. They have to be isbits, so zero is valid for
+            // them.
+            #[allow(unused_unsafe)]
+            let base: $t = unsafe { ::std::mem::zeroed() };
+            let start = &base as *const $t as usize;
+            let layout = $crate::api::datatype::Layout {
+                size: ::std::mem::size_of::<$t>(),
+                align: ::std::mem::align_of::<$t>(),
+                fields: vec![
+                    $(
+                        $crate::api::datatype::FieldLayout {
+                            offset: &base.$field as *const _ as usize - start,
+                            size: $crate::api::datatype::size_of_pointee(
+                                &base.$field as *const _,
+                            ),
+                        }
+                    ),*
+                ],
+                field_types: vec![
+                    $(
+                        $crate::api::datatype::field_datatype(&base.$field as *const _)
+                    ),*
+                ],
+            };
+            ::std::mem::forget(base);
+            layout
+        }
+    };
+}
+
+/// Trait implemented by Rust types with the same memory layout as an isbits
+/// Julia type. Values of these types can be shared with Julia without boxing,
+/// e.g. as elements of an Array.
+///
+/// This trait is unsafe, because implementing it for a type with a layout
+/// different from its Datatype leads to undefined behaviour. Compare the
+/// layouts with `Datatype::check_layout` and `jl_layout!` before
+/// implementing it for a `#[repr(C)]` struct.
+pub unsafe trait IsBits: Copy {
+    /// Returns the Julia Datatype corresponding to this type.
+    fn datatype() -> Result<Datatype>;
+}
+
+macro_rules! impl_isbits {
+    { $( $t:ty => $dt:ident ),* } => {
+        $(
+            unsafe impl IsBits for $t {
+                fn datatype() -> Result<Datatype> {
+                    Ok(Datatype::$dt())
+                }
+            }
+        )*
+    }
+}
+
+impl_isbits! {
+    bool => bool,
+    i8 => int8,
+    u8 => uint8,
+    i16 => int16,
+    u16 => uint16,
+    i32 => int32,
+    u32 => uint32,
+    i64 => int64,
+    u64 => uint64,
+    f32 => float32,
+    f64 => float64
+}
+
+unsafe impl IsBits for isize {
+    fn datatype() -> Result<Datatype> {
+        Datatype::new(unsafe { jl_long_type })
+    }
+}
+
+unsafe impl IsBits for usize {
+    fn datatype() -> Result<Datatype> {
+        Datatype::new(unsafe { jl_ulong_type })
+    }
+}
+
+unsafe impl<T: IsBits + Number + Real> IsBits for Complex<T> {
+    fn datatype() -> Result<Datatype> {
+        let complex = Type::from_value(Datatype::complex())?;
+        let param = Value::from_value(T::datatype()?)?;
+        complex.apply_type1(&param)?.into_value()
+    }
+}
+
+unsafe impl<T: IsBits + Number + Real + Integer> IsBits for Rational<T> {
+    fn datatype() -> Result<Datatype> {
+        let rational = Type::from_value(Datatype::rational()?)?;
+        let param = Value::from_value(T::datatype()?)?;
+        rational.apply_type1(&param)?.into_value()
+    }
+}
+
 impl Union {
     /// Create a union of types.
     pub fn union<'a, I>(ts: I) -> Result<Union>
@@ -0,0 +1,274 @@
+//! Module providing `JuliaValue`, a Rust representation of arbitrary Julia
+//! values.
+//!
+//! Any `Value` built from nothing, booleans, integers, floats, chars, strings,
+//! symbols, arrays, tuples, dicts and structs can be read into a tree of
+//! `JuliaValue`s, e.g. for logging or for generic tooling, and turned back
+//! into a Julia value.
+
+use std::convert::TryFrom;
+
+use sys::*;
+use error::{Result, Error};
+use api::{Value, JlValue, Array, Datatype, Scope};
+use api::collection::{helper, dict_entries};
+use api::convert::{IntoJulia, FromJulia, field_at, lookup_datatype, new_struct_by_name, symbol,
+                   symbol_name};
+
+/// How deeply values are read by `JuliaValue::new`.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// A Julia value represented in Rust.
+///
+/// Signed and unsigned integers and floats of any size are widened into
+/// `Int`, `UInt` and `Float`. Arrays of any dimension are read in
+/// column-major order and become `Vector`s when turned back into Julia
+/// values.
+#[derive(Clone, PartialEq, Debug)]
+pub enum JuliaValue {
+    /// `nothing`.
+    Nothing,
+    /// A `Bool`.
+    Bool(bool),
+    /// An `Int8`, `Int16`, `Int32` or `Int64`.
+    Int(i64),
+    /// A `UInt8`, `UInt16`, `UInt32` or `UInt64`.
+    UInt(u64),
+    /// A `Float32` or `Float64`.
+    Float(f64),
+    /// A `Char`.
+    Char(char),
+    /// A `String`.
+    String(String),
+    /// A `Symbol`, holding its name.
+    Symbol(String),
+    /// The elements of an `Array`.
+    Array(Vec<JuliaValue>),
+    /// The elements of a `Tuple`.
+    Tuple(Vec<JuliaValue>),
+    /// The entries of a `Dict` or any other `AbstractDict`.
+    Dict(Vec<(JuliaValue, JuliaValue)>),
+    /// A type, holding its name, e.g. `Float64` or `Vector{Any}`.
+    Type(String),
+    /// An instance of any other struct type.
+    Struct {
+        /// Name of the type, relative to `Main`.
+        type_name: String,
+        /// Names and values of the fields.
+        fields: Vec<(String, JuliaValue)>,
+    },
+}
+
+impl JuliaValue {
+    /// Reads `value`, which may be nested at most `DEFAULT_MAX_DEPTH` levels
+    /// deep.
+    ///
+    /// ## Errors
+    ///
+    /// See `JuliaValue::with_max_depth`.
+    pub fn new(value: &Value) -> Result<JuliaValue> {
+        JuliaValue::with_max_depth(value, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Reads `value`, which may be nested at most `max_depth` levels deep.
+    /// The limit protects against cyclic values, e.g. a mutable struct
+    /// referring to itself.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::DepthLimit if `value` is nested more deeply and
+    /// Error::TypeMismatch if `value` contains a value which can't be
+    /// represented, e.g. a module.
+    pub fn with_max_depth(value: &Value, max_depth: usize) -> Result<JuliaValue> {
+        let value = if value.is_nothing() {
+            JuliaValue::Nothing
+        } else if value.is_bool() {
+            JuliaValue::Bool(bool::try_from(value)?)
+        } else if value.is_int8() {
+            JuliaValue::Int(i8::try_from(value)? as i64)
+        } else if value.is_int16() {
+            JuliaValue::Int(i16::try_from(value)? as i64)
+        } else if value.is_int32() {
+            JuliaValue::Int(i32::try_from(value)? as i64)
+        } else if value.is_int64() {
+            JuliaValue::Int(i64::try_from(value)?)
+        } else if value.is_uint8() {
+            JuliaValue::UInt(u8::try_from(value)? as u64)
+        } else if value.is_uint16() {
+            JuliaValue::UInt(u16::try_from(value)? as u64)
+        } else if value.is_uint32() {
+            JuliaValue::UInt(u32::try_from(value)? as u64)
+        } else if value.is_uint64() {
+            JuliaValue::UInt(u64::try_from(value)?)
+        } else if value.is_float32() {
+            JuliaValue::Float(f32::try_from(value)? as f64)
+        } else if value.is_float64() {
+            JuliaValue::Float(f64::try_from(value)?)
+        } else if value.isa(&Datatype::char())? {
+            JuliaValue::Char(char::try_from(value)?)
+        } else if value.is_string() {
+            JuliaValue::String(String::try_from(value)?)
+        } else if let Some(name) = symbol_name(value)? {
+            JuliaValue::Symbol(name)
+        } else if value.is_type() {
+            JuliaValue::Type(String::try_from(&helper("typestring")?.call(&[value.clone()])?)?)
+        } else if max_depth == 0 {
+            return Err(Error::DepthLimit);
+        } else {
+            return read_collection(value, max_depth - 1);
+        };
+        Ok(value)
+    }
+
+    /// Constructs a new Julia value from self.
+    ///
+    /// Arrays become `Vector`s and dicts `Dict`s, their element types are
+    /// narrowed to the types of the elements. Structs are constructed by
+    /// looking up their type by name, so parametric types can't be
+    /// constructed.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::TypeMismatch if the type of a struct isn't found and
+    /// Error::FieldMismatch if its fields don't match.
+    pub fn to_value(&self) -> Result<Value> {
+        match *self {
+            JuliaValue::Nothing => Ok(Value::nothing()),
+            JuliaValue::Bool(b) => Ok(Value::from(b)),
+            JuliaValue::Int(i) => Ok(Value::from(i)),
+            JuliaValue::UInt(u) => Ok(Value::from(u)),
+            JuliaValue::Float(f) => Ok(Value::from(f)),
+            JuliaValue::Char(c) => Ok(Value::from(c)),
+            JuliaValue::String(ref s) => Ok(Value::from(s.as_str())),
+            JuliaValue::Symbol(ref name) => symbol(name),
+            JuliaValue::Array(ref elements) => Scope::enter(|scope| {
+                let elements = scope.root(to_vector(elements)?)?;
+                helper("vector")?.call(&[elements.get().clone()])
+            }),
+            JuliaValue::Tuple(ref elements) => Scope::enter(|scope| {
+                let elements = scope.root(to_vector(elements)?)?;
+                helper("totuple")?.call(&[elements.get().clone()])
+            }),
+            JuliaValue::Dict(ref entries) => {
+                let keys: Vec<_> = entries.iter().map(|entry| &entry.0).collect();
+                let values: Vec<_> = entries.iter().map(|entry| &entry.1).collect();
+                Scope::enter(|scope| {
+                    let keys = scope.root(to_vector(keys)?)?;
+                    let values = scope.root(to_vector(values)?)?;
+                    helper("dict")?.call(&[keys.get().clone(), values.get().clone()])
+                })
+            }
+            JuliaValue::Type(ref name) => lookup_datatype(name)?.to_value(),
+            JuliaValue::Struct {
+                ref type_name,
+                ref fields,
+            } => {
+                let datatype = lookup_datatype(type_name)?;
+                Scope::enter(|scope| {
+                    let mut values = vec![];
+                    for &(ref name, ref field) in fields {
+                        let field = scope.root(field.to_value()?)?;
+                        values.push((name.as_str(), field.get().clone()));
+                    }
+                    new_struct_by_name(&datatype, &values)
+                })
+            }
+        }
+    }
+}
+
+/// Reads an array, tuple, dict or struct.
+fn read_collection(value: &Value, max_depth: usize) -> Result<JuliaValue> {
+    let read = |element: &Value| JuliaValue::with_max_depth(element, max_depth);
+
+    if value.is_array() {
+        let array = Array::new(value.lock()? as *mut jl_array_t)?;
+        let elements = Scope::enter(|scope| {
+            let mut elements = vec![];
+            for idx in 0..array.len()? {
+                elements.push(read(scope.root(array.element(idx)?)?.get())?);
+            }
+            Ok(elements)
+        })?;
+        return Ok(JuliaValue::Array(elements));
+    }
+
+    if value.is_tuple() {
+        let elements = Scope::enter(|scope| {
+            let mut elements = vec![];
+            for idx in 0..value.datatype()?.nfields()? {
+                elements.push(read(scope.root(field_at(value, idx)?)?.get())?);
+            }
+            Ok(elements)
+        })?;
+        return Ok(JuliaValue::Tuple(elements));
+    }
+
+    let entries = Scope::enter(|scope| match dict_entries(scope, value)? {
+        Some((keys, values)) => {
+            let mut entries = vec![];
+            for idx in 0..keys.len()? {
+                let key = read(&keys.element(idx)?)?;
+                let entry = read(&values.element(idx)?)?;
+                entries.push((key, entry));
+            }
+            Ok(Some(entries))
+        }
+        None => Ok(None),
+    })?;
+    if let Some(entries) = entries {
+        return Ok(JuliaValue::Dict(entries));
+    }
+
+    let datatype = value.datatype()?;
+    if datatype.is_structtype() {
+        let type_name = String::try_from(&helper("typestring")?.call(&[datatype.to_value()?])?)?;
+        let fields = Scope::enter(|scope| {
+            let mut fields = vec![];
+            for (idx, name) in datatype.field_names()?.into_iter().enumerate() {
+                let field = read(scope.root(field_at(value, idx)?)?.get())?;
+                fields.push((name, field));
+            }
+            Ok(fields)
+        })?;
+        return Ok(JuliaValue::Struct {
+            type_name: type_name,
+            fields: fields,
+        });
+    }
+
+    Err(Error::TypeMismatch)
+}
+
+/// Constructs a `Vector{Any}` of new Julia values.
+fn to_vector<'a, I>(elements: I) -> Result<Value>
+where
+    I: IntoIterator<Item = &'a JuliaValue>,
+{
+    Scope::enter(|scope| {
+        let mut values = vec![];
+        for element in elements {
+            values.push(scope.root(element.to_value()?)?.get().clone());
+        }
+        Array::from_values(values)?.to_value()
+    })
+}
+
+impl<'a> TryFrom<&'a Value> for JuliaValue {
+    type Error = Error;
+    fn try_from(value: &Value) -> Result<JuliaValue> {
+        JuliaValue::new(value)
+    }
+}
+
+impl IntoJulia for JuliaValue {
+    fn into_julia(self) -> Result<Value> {
+        self.to_value()
+    }
+}
+
+impl FromJulia for JuliaValue {
+    fn from_julia(value: &Value) -> Result<JuliaValue> {
+        JuliaValue::new(value)
+    }
+}
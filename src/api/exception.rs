@@ -3,15 +3,19 @@
 
 use std::fmt;
 use std::error;
+use std::any::Any;
 use std::ops::Deref;
 use std::ops::DerefMut;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::os::raw::c_char;
 
 use smallvec::SmallVec;
 
 use sys::*;
-use error::Result;
+use error::{Result, Error};
 use string::IntoCString;
-use super::{Value, JlValue, Symbol, Datatype};
+use super::{Value, JlValue, Symbol, Datatype, Scope};
 
 /// Enum containing different Julia exceptions wrapped as a Value.
 #[derive(Clone)]
@@ -277,18 +281,88 @@ impl error::Error for Exception {
 
 /// Throws a generic error.
 pub fn error<S: IntoCString>(string: S) {
-    let string = string.into_cstring();
-    let string = string.as_ptr();
+    throw_raw(error_exception(string))
+}
+
+/// Creates an `ErrorException` with the message `string`. The message is
+/// copied into Julia, so nothing owned by Rust has to outlive the throw.
+fn error_exception<S: IntoCString>(string: S) -> *mut jl_value_t {
+    let ex = Scope::enter(|scope| {
+        let msg = scope.root(Value::from(string))?;
+        let datatype = unsafe { Datatype::new_unchecked(jl_errorexception_type) };
+        datatype.new_struct(&[msg.get().clone()])?.lock()
+    });
+
+    match ex {
+        Ok(ex) => ex,
+        Err(Error::UnhandledException(ex)) => ex.lock().unwrap_or(ptr::null_mut()),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Throws the raw exception `ex`, or a generic error if it's null.
+///
+/// `jl_throw` leaves with a longjmp, skipping the destructors of every Rust
+/// frame in between. Callers must drop everything they own before getting
+/// here, and nothing may allocate between creating `ex` and throwing it.
+#[inline(never)]
+fn throw_raw(ex: *mut jl_value_t) -> ! {
     unsafe {
-        jl_error(string);
+        if ex.is_null() {
+            jl_error(b"failed to create the exception\0".as_ptr() as *const c_char);
+        }
+        jl_throw(ex);
+    }
+    unreachable!("jl_throw returned")
+}
+
+/// Returns the message a panic was started with.
+pub(crate) fn panic_message(payload: &(Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        String::from("Rust code panicked")
     }
 }
 
+/// Runs `f` in a function called from Julia, turning a panic or an error into
+/// a Julia exception instead of unwinding into libjulia.
+///
+/// Exceptions are thrown with a longjmp, so the caller must not hold any
+/// values with destructors across the call.
+pub(crate) fn guard<T, F>(f: F) -> T
+where
+    F: FnOnce() -> Result<T>,
+{
+    // Every arm drops what it owns before the exception is thrown.
+    let ex = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => return value,
+        Ok(Err(Error::UnhandledException(ex))) => match ex.lock() {
+            Ok(ex) => ex,
+            Err(err) => error_exception(err.to_string()),
+        },
+        Ok(Err(err)) => error_exception(err.to_string()),
+        Err(payload) => error_exception(panic_message(&*payload)),
+    };
+    throw_raw(ex)
+}
+
 /// Throws a formatted generic error.
 pub fn error_format(args: fmt::Arguments) {
     error(fmt::format(args).into_cstring());
 }
 
+/// Throws `ex`, which can be any Julia value.
+pub fn throw(ex: &Value) -> Result<()> {
+    let ex = ex.lock()?;
+    unsafe {
+        jl_throw(ex);
+    }
+    Ok(())
+}
+
 /// Throws an exception with the specified Datatype and message.
 pub fn exception<S: IntoCString>(ty: &Datatype, string: S) -> Result<()> {
     let ty = ty.lock()?;
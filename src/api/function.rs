@@ -1,17 +1,104 @@
 
 //! Module providing a wrapper for the native Julia function object.
 
+use std::convert::TryFrom;
+
 use smallvec::SmallVec;
 
 use sys::*;
 use error::{Result, Error};
-use super::{JlValue, Value};
+use super::{JlValue, Value, Array, Datatype, Module, Svec, Scope, Symbol, IntoSymbol,
+            IntoJulia, FromJulia};
+use super::callback;
+use super::datatype::{Type, Tuple};
 
 jlvalues! {
     pub struct Function(jl_function_t);
+    pub struct Method(jl_method_t);
+}
+
+/// Name of the private module holding the helper for passing keyword
+/// arguments.
+const KWARGS_MODULE: &str = "__JuliaRsKwargs";
+
+/// Source of the private module holding the helper for passing keyword
+/// arguments. Kwsorters take the keyword arguments as a `NamedTuple` since
+/// Julia 0.7 and as a flat `Vector{Any}` of names and values before.
+const KWARGS_JL: &str = "module __JuliaRsKwargs
+if isdefined(Base, :NamedTuple)
+    kwargs(ks, vs) = NamedTuple{(ks...,)}((vs...,))
+else
+    kwargs(ks, vs) = Any[x for kv in zip(ks, vs) for x in kv]
+end
+end";
+
+/// Trait implemented by tuples of arguments for `Function::invoke`. Every
+/// element is converted with `IntoJulia`, like the result is converted with
+/// `FromJulia`.
+pub trait IntoArgs {
+    /// Converts self into argument Values rooted in `scope`.
+    fn into_args(self, scope: &Scope) -> Result<Vec<Value>>;
+}
+
+impl IntoArgs for () {
+    fn into_args(self, _: &Scope) -> Result<Vec<Value>> {
+        Ok(vec![])
+    }
+}
+
+/// Values are passed as they are, they have to be kept alive by the caller.
+impl<'a> IntoArgs for &'a [Value] {
+    fn into_args(self, _: &Scope) -> Result<Vec<Value>> {
+        Ok(self.to_vec())
+    }
+}
+
+macro_rules! into_args_tuple {
+    ($(($($t:ident $idx:tt),+)),*) => {
+        $(
+            impl<$($t: IntoJulia),+> IntoArgs for ($($t,)+) {
+                fn into_args(self, scope: &Scope) -> Result<Vec<Value>> {
+                    Ok(vec![$( scope.root(self.$idx.into_julia()?)?.get().clone() ),+])
+                }
+            }
+        )*
+    };
+}
+
+into_args_tuple! {
+    (A 0),
+    (A 0, B 1),
+    (A 0, B 1, C 2),
+    (A 0, B 1, C 2, D 3),
+    (A 0, B 1, C 2, D 3, E 4),
+    (A 0, B 1, C 2, D 3, E 4, F 5),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10),
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11)
 }
 
 impl Function {
+    /// Calls the function with a tuple of arguments and converts the result
+    /// into `R`, e.g. `let y: f64 = f.invoke((1.0, 2))?`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::ReturnMismatch if the result can't be converted into
+    /// `R`.
+    pub fn invoke<R: FromJulia, A: IntoArgs>(&self, args: A) -> Result<R> {
+        Scope::enter(|scope| {
+            let args = args.into_args(scope)?;
+            let ret = scope.root(self.call(&args)?)?;
+            R::from_julia(&ret).map_err(|err| match ret.typename() {
+                Ok(typename) => Error::ReturnMismatch(typename, Box::new(err)),
+                Err(_) => err,
+            })
+        })
+    }
+
     /// Call with a sequence of Value-s.
     pub fn call<'a, I>(&self, args: I) -> Result<Value>
     where
@@ -35,6 +122,7 @@ impl Function {
     }
 
     /// Call with 1 Value.
+    #[deprecated(note = "use `invoke` or `call` instead")]
     pub fn call1(&self, arg1: &Value) -> Result<Value> {
         let ret = unsafe { jl_call1(self.lock()?, arg1.lock()?) };
         jl_catch!();
@@ -42,6 +130,7 @@ impl Function {
     }
 
     /// Call with 2 Value-s.
+    #[deprecated(note = "use `invoke` or `call` instead")]
     pub fn call2(&self, arg1: &Value, arg2: &Value) -> Result<Value> {
         let ret = unsafe { jl_call2(self.lock()?, arg1.lock()?, arg2.lock()?) };
         jl_catch!();
@@ -49,9 +138,206 @@ impl Function {
     }
 
     /// Call with 3 Value-s.
+    #[deprecated(note = "use `invoke` or `call` instead")]
     pub fn call3(&self, arg1: &Value, arg2: &Value, arg3: &Value) -> Result<Value> {
         let ret = unsafe { jl_call3(self.lock()?, arg1.lock()?, arg2.lock()?, arg3.lock()?) };
         jl_catch!();
         Value::new(ret).map_err(|_| Error::CallError)
     }
+
+    /// Returns the signatures of the methods of this generic function. Each
+    /// signature is a `Tuple` type of the function and the argument types,
+    /// wrapped in `UnionAll`s for methods with type parameters.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::NotAFunction if the function has no method table and
+    /// Error::TypeMismatch if `Base.methods` returns something other than
+    /// methods.
+    pub fn methods(&self) -> Result<Vec<Type>> {
+        if unsafe { jl_gf_mtable(self.lock()?) }.is_null() {
+            return Err(Error::NotAFunction);
+        }
+
+        let base = unsafe { Module::new_unchecked(jl_base_module) };
+        let collect = base.function("collect")?;
+        let methods = base.function("methods")?;
+
+        Scope::enter(|scope| {
+            let methods = scope.root(methods.call(&[self.to_value()?])?)?;
+            let methods = scope.root(Array::from_value(collect.call(&[methods.get().clone()])?)?)?;
+
+            // The methods and their signatures are kept alive by the method
+            // table.
+            let mut sigs = vec![];
+            for idx in 0..methods.len()? {
+                let method = methods.element(idx)?;
+                if !method.is_method() {
+                    return Err(Error::TypeMismatch);
+                }
+                sigs.push(Method::new(method.lock()? as *mut jl_method_t)?.sig()?);
+            }
+            Ok(sigs)
+        })
+    }
+
+    /// Returns the method called for arguments of the types `arg_types`,
+    /// like `Base.which`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::UnhandledException if no unique method matches and
+    /// Error::TypeMismatch if `Base.which` returns something other than a
+    /// method.
+    pub fn which(&self, arg_types: &Tuple) -> Result<Method> {
+        let base = unsafe { Module::new_unchecked(jl_base_module) };
+        let which = base.function("which")?;
+        let method = which.call(&[self.to_value()?, arg_types.to_value()?])?;
+        if !method.is_method() {
+            return Err(Error::TypeMismatch);
+        }
+        Method::new(method.lock()? as *mut jl_method_t)
+    }
+
+    /// Defines a new method of this generic function taking one argument of
+    /// each of `arg_types`, whose body calls `f`. Returns the new method.
+    ///
+    /// This works for functions of any module, e.g. `Base.show` or
+    /// `Base.length`, so that types defined from Rust, like the wrapper
+    /// types of `RustBox`, can take part in Julia's generic functions.
+    ///
+    /// The closure is kept alive until the method is replaced by adding
+    /// another method with the same signature through `add_method`. If the
+    /// method is redefined from Julia code instead, the closure is never
+    /// freed. Errors returned by `f` and panics are thrown as Julia
+    /// exceptions.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::TypeMismatch if the new method can't be looked up. The
+    /// method is defined nonetheless and keeps calling `f`.
+    pub fn add_method<F>(&self, arg_types: &[Datatype], f: F) -> Result<Method>
+    where
+        F: Fn(&[Value]) -> Result<Value> + 'static,
+    {
+        let addmethod = callback::helper("addmethod")?;
+        let types = arg_types.iter().map(|t| t.to_value()).collect::<Result<Vec<_>>>()?;
+        let id = callback::register(f);
+
+        let defined = Scope::enter(|scope| {
+            let rust_fn = callback::rust_function(scope, id)?;
+            let types = scope.root(Array::from_values(types.clone())?.to_value()?)?;
+            addmethod.call(&[self.to_value()?, types.get().clone(), rust_fn.get().clone()])
+        });
+        if let Err(err) = defined {
+            // Nothing refers to the closure if the method wasn't defined.
+            callback::unregister(id);
+            return Err(err);
+        }
+
+        // The method is kept alive by the method table of the function.
+        let method = Scope::enter(|scope| {
+            let params = scope.root(Svec::from_values(types)?)?;
+            let arg_types = scope.root(Tuple::apply(&params)?)?;
+            self.which(&arg_types)
+        })?;
+        callback::register_method(self, &method, id)?;
+        Ok(method)
+    }
+
+    /// Calls the method matching the argument types `arg_types`, even if a
+    /// more specific method exists for the actual arguments, like
+    /// `Base.invoke`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::UnhandledException(Exception::Method(_)) if no method
+    /// matches `arg_types` or the arguments aren't of those types.
+    pub fn invoke_method<'a, I>(&self, arg_types: &Tuple, args: I) -> Result<Value>
+    where
+        I: IntoIterator<Item = &'a Value>,
+    {
+        let core = unsafe { Module::new_unchecked(jl_core_module) };
+        let invoke = core.function("invoke")?;
+
+        let mut argv = vec![self.to_value()?, arg_types.to_value()?];
+        argv.extend(args.into_iter().cloned());
+        invoke.call(&argv)
+    }
+
+    /// Call with a sequence of Value-s and keyword arguments given as
+    /// `(name, value)` pairs, like `f(args...; name=value, ...)` in Julia.
+    ///
+    /// The call is dispatched through the kwsorter of the function,
+    /// `Core.kwfunc(f)`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::UnhandledException(Exception::Method(_)) if the
+    /// function doesn't accept one of the keywords.
+    pub fn call_kw<'a, I, S>(&self, args: I, kwargs: &[(S, &Value)]) -> Result<Value>
+    where
+        I: IntoIterator<Item = &'a Value>,
+        S: IntoSymbol + Clone,
+    {
+        let mut names = vec![];
+        let mut values = vec![];
+        for &(ref name, value) in kwargs {
+            names.push(name.clone().into_symbol()?.to_value()?);
+            values.push(value.clone());
+        }
+
+        let core = unsafe { Module::new_unchecked(jl_core_module) };
+        let kwfunc = core.function("kwfunc")?;
+        let sort = Module::main_submodule(KWARGS_MODULE, KWARGS_JL)?.function("kwargs")?;
+
+        Scope::enter(|scope| {
+            let kwsorter = scope.root(kwfunc.call(&[self.to_value()?])?)?;
+            let names = scope.root(Array::from_values(names)?.to_value()?)?;
+            let values = scope.root(Array::from_values(values)?.to_value()?)?;
+            let kwargs = scope.root(sort.call(&[names.get().clone(), values.get().clone()])?)?;
+
+            let mut argv = SmallVec::<[*mut jl_value_t; 8]>::new();
+            argv.push(kwargs.lock()?);
+            argv.push(self.lock()? as *mut _);
+            for arg in args {
+                argv.push(arg.lock()?);
+            }
+
+            let ret = unsafe {
+                jl_call(kwsorter.lock()? as *mut _, argv.as_mut_ptr(), argv.len() as i32)
+            };
+            jl_catch!();
+            Value::new(ret).map_err(|_| Error::CallError)
+        })
+    }
+}
+
+impl Method {
+    /// Returns the name of the method.
+    pub fn name(&self) -> Result<String> {
+        let name = unsafe { (*self.lock()?).name };
+        String::try_from(&Symbol::new(name)?)
+    }
+
+    /// Returns the module the method was defined in.
+    pub fn module(&self) -> Result<Module> {
+        Module::new(unsafe { (*self.lock()?).module })
+    }
+
+    /// Returns the file the method was defined in.
+    pub fn file(&self) -> Result<String> {
+        let file = unsafe { (*self.lock()?).file };
+        String::try_from(&Symbol::new(file)?)
+    }
+
+    /// Returns the line the method was defined on.
+    pub fn line(&self) -> Result<u32> {
+        Ok(unsafe { (*self.lock()?).line } as u32)
+    }
+
+    /// Returns the signature of the method, see `Function::methods`.
+    pub fn sig(&self) -> Result<Type> {
+        Type::new(unsafe { (*self.lock()?).sig })
+    }
 }
@@ -0,0 +1,374 @@
+//! Module providing bridges between Rust iterators and Julia iterables.
+
+use std::convert::TryFrom;
+use std::os::raw::c_void;
+
+use sys::*;
+use error::{Result, Error};
+use api::{Value, JlValue, Function, Module, Array, Svec, Datatype, PinnedValue, Scope, IntoSymbol};
+use api::datatype::TypeBuilder;
+use api::exception::guard;
+
+/// Name of the private module holding the wrapper types for Rust iterators.
+const ITER_MODULE: &str = "__JuliaRsIter";
+
+/// Source of the private module holding the wrapper types for Rust iterators.
+/// The concrete wrappers are defined from Rust as mutable subtypes of
+/// `AbstractRustIterator`, with the fields `next`, `drop`, `data`, `eltype`
+/// and, for subtypes of `AbstractSizedRustIterator`, `len`. Items are pulled
+/// through `next` until it returns the `END` sentinel, the iterator itself is
+/// freed by `release` once Julia collects the wrapper.
+const ITER_JL: &str = "module __JuliaRsIter
+abstract type AbstractRustIterator end
+abstract type AbstractSizedRustIterator <: AbstractRustIterator end
+
+struct End end
+const END = End()
+
+release(it::AbstractRustIterator) = ccall(it.drop, Void, (Ptr{Void},), it.data)
+advance(it::AbstractRustIterator) = ccall(it.next, Any, (Ptr{Void}, Any), it.data, END)
+
+Base.eltype(it::AbstractRustIterator) = it.eltype
+Base.length(it::AbstractSizedRustIterator) = Int(ccall(it.len, UInt, (Ptr{Void},), it.data))
+
+const SIZE = isdefined(Base, :IteratorSize) ? :IteratorSize : :iteratorsize
+@eval Base.$SIZE(::Type{<:AbstractRustIterator}) = Base.SizeUnknown()
+@eval Base.$SIZE(::Type{<:AbstractSizedRustIterator}) = Base.HasLength()
+
+if isdefined(Base, :iterate)
+    function Base.iterate(it::AbstractRustIterator, state = nothing)
+        x = advance(it)
+        x === END ? nothing : (x, nothing)
+    end
+else
+    # The state is the next item, fetched one step ahead for `done`.
+    Base.start(it::AbstractRustIterator) = advance(it)
+    Base.done(::AbstractRustIterator, x) = x === END
+    Base.next(it::AbstractRustIterator, x) = (x, advance(it))
+end
+end";
+
+/// Returns the next item of the iterator behind `data`, or `end` once it's
+/// exhausted.
+extern "C" fn next_item<I>(data: *mut c_void, end: *mut jl_value_t) -> *mut jl_value_t
+where
+    I: Iterator,
+    I::Item: Into<Value>,
+{
+    guard(|| {
+        let iter = unsafe { &mut *(data as *mut I) };
+        match iter.next() {
+            Some(item) => {
+                // The handle is dropped when the closure returns, nothing
+                // allocates before Julia receives the pointer.
+                let item: Value = item.into();
+                item.lock()
+            }
+            None => Ok(end),
+        }
+    })
+}
+
+/// Returns the number of items left in the iterator behind `data`.
+extern "C" fn remaining<I: ExactSizeIterator>(data: *mut c_void) -> usize {
+    guard(|| {
+        let iter = unsafe { &*(data as *const I) };
+        Ok(iter.len())
+    })
+}
+
+/// Frees an iterator previously leaked by `wrap_iter`.
+extern "C" fn drop_iter<I>(data: *mut c_void) {
+    guard(|| {
+        unsafe {
+            drop(Box::from_raw(data as *mut I));
+        }
+        Ok(())
+    })
+}
+
+/// Returns the wrapper type `name`, defining it on first use. Sized wrappers
+/// get an additional `len` field.
+fn iter_type(module: &Module, name: &str, sized: bool) -> Result<Datatype> {
+    if let Ok(datatype) = module.global(name) {
+        return Datatype::from_value(datatype);
+    }
+
+    let supertype = if sized {
+        "AbstractSizedRustIterator"
+    } else {
+        "AbstractRustIterator"
+    };
+    let supertype = Datatype::from_value(module.global(supertype)?)?;
+
+    let mut fields = vec![
+        ("next", Datatype::void_pointer()),
+        ("drop", Datatype::void_pointer()),
+        ("data", Datatype::void_pointer()),
+        ("eltype", Datatype::any()),
+    ];
+    if sized {
+        fields.push(("len", Datatype::void_pointer()));
+    }
+
+    let mut fnames = vec![];
+    let mut ftypes = vec![];
+    for (fname, ftype) in fields {
+        fnames.push(Value::from_value(fname.into_symbol()?)?);
+        ftypes.push(ftype.to_value()?);
+    }
+
+    Scope::enter(|scope| {
+        let fnames = scope.root(Svec::from_values(fnames)?)?;
+        let ftypes = scope.root(Svec::from_values(ftypes)?)?;
+        let datatype = TypeBuilder::new()
+            .name(name)
+            .supertype(&supertype)
+            .fnames(&fnames)
+            .ftypes(&ftypes)
+            .mutable(true)
+            .build()?;
+        let datatype = scope.root(datatype)?;
+
+        // Types aren't rooted anywhere else, binding them also makes them
+        // show up with a readable name.
+        module.set_const(name, &datatype.to_value()?)?;
+        Ok(datatype.get().clone())
+    })
+}
+
+fn box_ptr(ptr: *mut c_void) -> Result<Value> {
+    let raw = unsafe { jl_box_voidpointer(ptr as *mut _) };
+    jl_catch!();
+    Value::new(raw)
+}
+
+/// Moves `iter` into a new instance of the Julia type `type_name`.
+fn wrap_iter<I>(
+    type_name: &str,
+    iter: I,
+    eltype: Option<&Datatype>,
+    len: Option<*mut c_void>,
+) -> Result<Value>
+where
+    I: Iterator + 'static,
+    I::Item: Into<Value>,
+{
+    let module = Module::main_submodule(ITER_MODULE, ITER_JL)?;
+    let iter_type = iter_type(&module, type_name, len.is_some())?;
+    let release = module.function("release")?;
+    let eltype = match eltype {
+        Some(eltype) => eltype.to_value()?,
+        None => Datatype::any().to_value()?,
+    };
+
+    Scope::enter(move |scope| {
+        let next =
+            next_item::<I> as extern "C" fn(*mut c_void, *mut jl_value_t) -> *mut jl_value_t;
+        let next = scope.root(box_ptr(next as *mut c_void)?)?;
+        let drop_fn = drop_iter::<I> as extern "C" fn(*mut c_void);
+        let drop_fn = scope.root(box_ptr(drop_fn as *mut c_void)?)?;
+        let eltype = scope.root(eltype)?;
+
+        // From here on Julia owns the iterator, if anything fails it's
+        // leaked rather than freed twice.
+        let data = Box::into_raw(Box::new(iter)) as *mut c_void;
+        let data = scope.root(box_ptr(data)?)?;
+
+        let mut fields = vec![
+            next.get().clone(),
+            drop_fn.get().clone(),
+            data.get().clone(),
+            eltype.get().clone(),
+        ];
+        if let Some(len) = len {
+            let len = scope.root(box_ptr(len)?)?;
+            fields.push(len.get().clone());
+        }
+
+        let wrapper = scope.root(iter_type.new_struct(&fields)?)?;
+        wrapper.add_finalizer(&release)?;
+        Ok(wrapper.get().clone())
+    })
+}
+
+/// Wraps a Rust iterator in a lazy Julia iterable.
+///
+/// Julia code can loop over the returned value, and every item is only
+/// produced when Julia asks for it. `eltype` is what Julia's `eltype` reports
+/// and defaults to `Any`. The iterator is dropped when the value is collected.
+pub fn from_rust_iter<I>(iter: I, eltype: Option<&Datatype>) -> Result<Value>
+where
+    I: Iterator + 'static,
+    I::Item: Into<Value>,
+{
+    wrap_iter("RustIterator", iter, eltype, None)
+}
+
+/// Same as `from_rust_iter`, but the Julia iterable also supports `length`.
+pub fn from_exact_rust_iter<I>(iter: I, eltype: Option<&Datatype>) -> Result<Value>
+where
+    I: ExactSizeIterator + 'static,
+    I::Item: Into<Value>,
+{
+    let len = remaining::<I> as extern "C" fn(*mut c_void) -> usize;
+    wrap_iter("SizedRustIterator", iter, eltype, Some(len as *mut c_void))
+}
+
+/// The iteration protocol implemented by the running Julia version.
+enum Protocol {
+    /// `iterate(x[, state])`, returning `nothing` once exhausted.
+    Iterate(Function),
+    /// `start(x)`, `done(x, state)` and `next(x, state)`.
+    StartNextDone {
+        start: Function,
+        done: Function,
+        next: Function,
+    },
+}
+
+impl Protocol {
+    fn get() -> Result<Protocol> {
+        let base = unsafe { Module::new_unchecked(jl_base_module) };
+
+        if let Ok(iterate) = base.function("iterate") {
+            return Ok(Protocol::Iterate(iterate));
+        }
+
+        Ok(Protocol::StartNextDone {
+            start: base.function("start")?,
+            done: base.function("done")?,
+            next: base.function("next")?,
+        })
+    }
+}
+
+/// Splits a 2-tuple into its elements.
+fn unpack_pair(tuple: &Value, scope: &Scope) -> Result<(Value, Value)> {
+    if !tuple.is_tuple() {
+        return Err(Error::TypeMismatch);
+    }
+    let raw = tuple.lock()?;
+
+    let first = unsafe { jl_fieldref(raw, 0) };
+    jl_catch!();
+    let first = scope.root(Value::new(first)?)?;
+    let second = unsafe { jl_fieldref(raw, 1) };
+    jl_catch!();
+
+    Ok((first.get().clone(), Value::new(second)?))
+}
+
+/// Iterator over any Julia iterable, created by `Value::iter`.
+///
+/// Every item is obtained lazily by calling Julia's iteration protocol. The
+/// iterable and the iteration state are pinned for as long as the iterator
+/// lives.
+pub struct JlIter {
+    protocol: Protocol,
+    /// A `[iterable, state]` Vector keeping both alive between calls.
+    roots: PinnedValue,
+    started: bool,
+    finished: bool,
+}
+
+impl JlIter {
+    /// Construct a new iterator over `iterable`.
+    pub fn new(iterable: Value) -> Result<JlIter> {
+        let roots = Array::from_values(vec![iterable, Value::nothing()])?;
+        let roots = roots.to_value()?.pin()?;
+
+        Ok(JlIter {
+            protocol: Protocol::get()?,
+            roots: roots,
+            started: false,
+            finished: false,
+        })
+    }
+
+    fn roots(&self) -> Result<Array> {
+        Array::new(self.roots.lock()? as *mut jl_array_t)
+    }
+
+    fn iterable(&self) -> Result<Value> {
+        self.roots()?.element(0)
+    }
+
+    fn state(&self) -> Result<Value> {
+        self.roots()?.element(1)
+    }
+
+    fn set_state(&self, state: &Value) -> Result<()> {
+        self.roots()?.index_set(1, state)
+    }
+
+    fn advance(&mut self) -> Result<Option<Value>> {
+        let iterable = self.iterable()?;
+
+        Scope::enter(|scope| {
+            let item = match self.protocol {
+                Protocol::Iterate(ref iterate) => {
+                    let ret = if self.started {
+                        iterate.call(&[iterable.clone(), self.state()?])?
+                    } else {
+                        iterate.call(&[iterable.clone()])?
+                    };
+                    if ret.is_nothing() {
+                        return Ok(None);
+                    }
+
+                    let ret = scope.root(ret)?;
+                    let (item, state) = unpack_pair(&ret, scope)?;
+                    let item = scope.root(item)?;
+                    self.set_state(&state)?;
+                    item.get().clone()
+                }
+                Protocol::StartNextDone {
+                    ref start,
+                    ref done,
+                    ref next,
+                } => {
+                    if !self.started {
+                        self.set_state(&start.call(&[iterable.clone()])?)?;
+                    }
+
+                    let state = self.state()?;
+                    if bool::try_from(&done.call(&[iterable.clone(), state.clone()])?)? {
+                        return Ok(None);
+                    }
+
+                    let ret = scope.root(next.call(&[iterable.clone(), state.clone()])?)?;
+                    let (item, state) = unpack_pair(&ret, scope)?;
+                    let item = scope.root(item)?;
+                    self.set_state(&state)?;
+                    item.get().clone()
+                }
+            };
+
+            self.started = true;
+            Ok(Some(item))
+        })
+    }
+}
+
+impl Iterator for JlIter {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Result<Value>> {
+        if self.finished {
+            return None;
+        }
+
+        match self.advance() {
+            Ok(Some(item)) => Some(Ok(item)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
@@ -41,16 +41,30 @@ pub mod datatype;
 pub mod task;
 pub mod exception;
 pub mod primitive;
-
-pub use self::value::{Value, JlValue};
-pub use self::array::{Array, Svec};
-pub use self::function::Function;
+pub mod scope;
+pub mod iter;
+pub mod callback;
+pub mod opaque;
+pub mod convert;
+pub mod collection;
+pub mod dynamic;
+#[cfg(feature = "serde")]
+pub mod serde;
+
+pub use self::value::{Value, JlValue, PinnedValue};
+pub use self::array::{Array, TypedArray, BorrowedArray, ByteArray, Svec};
+pub use self::function::{Function, IntoArgs, Method};
 pub use self::sym::{Symbol, IntoSymbol};
 pub use self::module::Module;
-pub use self::datatype::Datatype;
+pub use self::datatype::{Datatype, IsBits};
 pub use self::task::Task;
 pub use self::exception::Exception;
 pub use self::primitive::*;
+pub use self::scope::{Scope, Rooted};
+pub use self::iter::JlIter;
+pub use self::opaque::{RustBox, BoxRef};
+pub use self::convert::{IntoJulia, FromJulia, JuliaType};
+pub use self::dynamic::JuliaValue;
 
 /// Blank struct for controlling the Julia garbage collector.
 pub struct Gc;
@@ -192,6 +206,15 @@ impl Julia {
         &mut self.gc
     }
 
+    /// Runs `f` inside a new GC scope. Values rooted with `Scope::root` are
+    /// protected from the garbage collector until `f` returns.
+    pub fn scope<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Julia, &Scope) -> Result<T>,
+    {
+        Scope::enter(|scope| f(self, scope))
+    }
+
     /// Checks if Julia was already initialized in the current thread.
     pub fn is_initialized() -> bool {
         unsafe { jl_is_initialized() != 0 }
@@ -259,6 +282,11 @@ impl Julia {
 
 impl Drop for Julia {
     fn drop(&mut self) {
-        self.at_exit.map(|s| unsafe { jl_atexit_hook(s) });
+        if let Some(status) = self.at_exit {
+            unsafe {
+                jl_atexit_hook(status);
+            }
+            value::set_exited();
+        }
     }
 }
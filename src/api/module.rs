@@ -3,13 +3,47 @@
 
 use sys::*;
 use error::Result;
+use string::IntoCString;
 use super::{Value, JlValue, Function, IntoSymbol};
 
 jlvalues! {
     pub struct Module(jl_module_t);
 }
 
+/// Name of the private module used to evaluate code in other modules.
+const EVAL_MODULE: &str = "__JuliaRsEval";
+
+/// Source of the private module used to evaluate code in other modules.
+const EVAL_JL: &str = "module __JuliaRsEval
+parse_string(code) = isdefined(Meta, :parse) ? Meta.parse(code) : parse(code)
+evalin(m::Module, code::AbstractString) = Core.eval(m, parse_string(code))
+end";
+
 impl Module {
+    /// Returns the module `Main.<name>`, evaluating its definition `src`
+    /// first if it doesn't exist yet. Used for julia-rs' private helper
+    /// modules.
+    pub(crate) fn main_submodule(name: &str, src: &str) -> Result<Module> {
+        let main = unsafe { Module::new_unchecked(jl_main_module) };
+        if let Ok(module) = main.global(name) {
+            return Module::from_value(module);
+        }
+
+        let src = src.into_cstring();
+        unsafe {
+            jl_eval_string(src.as_ptr());
+        }
+        jl_catch!();
+        main.global(name).and_then(Module::from_value)
+    }
+
+    /// Parses and evaluates a single expression inside this module.
+    pub fn eval_string<S: IntoCString>(&self, string: S) -> Result<Value> {
+        let evalin = Module::main_submodule(EVAL_MODULE, EVAL_JL)?.function("evalin")?;
+        let string = Value::from(string);
+        evalin.call(&[self.to_value()?, string])
+    }
+
     /// Returns a global bound to the symbol `sym`.
     pub fn global<S: IntoSymbol>(&self, sym: S) -> Result<Value> {
         let module = self.lock()?;
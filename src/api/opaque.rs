@@ -0,0 +1,223 @@
+//! Module providing opaque handles to Rust values owned by Julia.
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::os::raw::c_void;
+use std::rc::Rc;
+
+use sys::*;
+use error::{Result, Error};
+use api::{Value, JlValue, Datatype, Function, Module, PinnedValue, Scope};
+use api::datatype::TypeBuilder;
+use api::exception::guard;
+
+/// Name of the private module holding the wrapper types of boxed Rust values.
+const BOXES_MODULE: &str = "__JuliaRsBoxes";
+
+/// Source of the private module holding the wrapper types of boxed Rust
+/// values. Every Rust type gets its own mutable, field-less subtype of
+/// `AbstractRustBox`. The boxed values themselves are kept on the Rust side,
+/// so Julia code can't tamper with the pointers. A `Release` is registered as
+/// the finalizer of every box and calls back into Rust to drop the value.
+const BOXES_JL: &str = "module __JuliaRsBoxes
+abstract type AbstractRustBox end
+struct Release
+    call::Ptr{Void}
+end
+(r::Release)(b::AbstractRustBox) = ccall(r.call, Void, (Any,), b)
+end";
+
+/// A boxed value and the function dropping it. The value is dropped along
+/// with this struct.
+struct Boxed {
+    ptr: *mut c_void,
+    drop: fn(*mut c_void),
+}
+
+impl Drop for Boxed {
+    fn drop(&mut self) {
+        (self.drop)(self.ptr);
+    }
+}
+
+thread_local! {
+    /// Julia wrapper types by the `TypeId` of the boxed Rust type.
+    static TYPES: RefCell<HashMap<TypeId, Datatype>> = RefCell::new(HashMap::new());
+
+    /// Boxed values by the address of the Julia object holding them. Julia
+    /// never moves objects, so the address identifies a box until it's
+    /// finalized. Borrows handed out by `RustBox::get` share the value, so it
+    /// outlives finalization while they're in use.
+    static BOXES: RefCell<HashMap<usize, Rc<Boxed>>> = RefCell::new(HashMap::new());
+}
+
+/// Drops a value previously leaked by `RustBox::new`.
+fn drop_box<T>(ptr: *mut c_void) {
+    unsafe {
+        drop(Box::from_raw(ptr as *mut T));
+    }
+}
+
+/// Removes the value boxed in the object at `key`, dropping it unless it's
+/// still borrowed. Does nothing if it was already removed, e.g. because
+/// Julia code called `finalize` on the box before.
+fn remove_box(key: usize) {
+    // The value is dropped after the registry is released, so its destructor
+    // can use boxes too.
+    let boxed = BOXES.with(|boxes| boxes.borrow_mut().remove(&key));
+    drop(boxed);
+}
+
+/// The finalizer of boxes, see `remove_box`.
+extern "C" fn release_box(b: *mut jl_value_t) {
+    guard(|| {
+        remove_box(b as usize);
+        Ok(())
+    })
+}
+
+/// Defines a new wrapper type for boxed Rust values.
+fn new_box_type(module: &Module, name: &str) -> Result<Datatype> {
+    let supertype = Datatype::from_value(module.global("AbstractRustBox")?)?;
+
+    Scope::enter(|scope| {
+        let datatype = TypeBuilder::new()
+            .name(name)
+            .supertype(&supertype)
+            .mutable(true)
+            .build()?;
+        let datatype = scope.root(datatype)?;
+
+        // Types aren't rooted anywhere else, binding them also makes them
+        // show up with a readable name.
+        module.set_const(name, &datatype.to_value()?)?;
+        Ok(datatype.get().clone())
+    })
+}
+
+/// A Rust value boxed inside a Julia object.
+///
+/// Julia code can pass the handle around freely, but can't look inside it.
+/// The Rust value is dropped once Julia collects the object. Every Rust type
+/// gets its own Julia type, so a `Value` received from Julia can only be
+/// turned back into a `RustBox` of the type it was created with.
+///
+/// The Julia object is pinned for as long as the `RustBox` lives. If Julia
+/// code explicitly calls `finalize` on it, the value is dropped once the last
+/// `BoxRef` borrowing it is gone. Values are only ever shared, use a `Cell`
+/// or `RefCell` for interior mutability.
+pub struct RustBox<T> {
+    value: PinnedValue,
+    _marker: PhantomData<T>,
+}
+
+impl<T: 'static> RustBox<T> {
+    /// Returns the Julia type wrapping `T`, defining it if necessary.
+    pub fn datatype() -> Result<Datatype> {
+        let id = TypeId::of::<T>();
+        if let Some(datatype) = TYPES.with(|types| types.borrow().get(&id).cloned()) {
+            return Ok(datatype);
+        }
+
+        let module = Module::main_submodule(BOXES_MODULE, BOXES_JL)?;
+        let name = format!("RustBox{}", TYPES.with(|types| types.borrow().len()));
+        let datatype = new_box_type(&module, &name)?;
+
+        TYPES.with(|types| types.borrow_mut().insert(id, datatype.clone()));
+        Ok(datatype)
+    }
+
+    /// Moves `data` into a new Julia object.
+    pub fn new(data: T) -> Result<RustBox<T>> {
+        let datatype = RustBox::<T>::datatype()?;
+        let module = Module::main_submodule(BOXES_MODULE, BOXES_JL)?;
+        let release_type = Datatype::from_value(module.global("Release")?)?;
+
+        Scope::enter(move |scope| {
+            let call = release_box as extern "C" fn(*mut jl_value_t);
+            let call = unsafe { jl_box_voidpointer(call as *mut c_void as *mut _) };
+            let call = scope.root(Value::new(call)?)?;
+            let release = scope.root(release_type.new_struct(&[call.get().clone()])?)?;
+            let release = Function::new(release.lock()? as *mut jl_function_t)?;
+            let value = scope.root(datatype.new_struct(&[])?)?;
+
+            let boxed = Boxed {
+                ptr: Box::into_raw(Box::new(data)) as *mut c_void,
+                drop: drop_box::<T>,
+            };
+            let key = value.lock()? as usize;
+            BOXES.with(|boxes| boxes.borrow_mut().insert(key, Rc::new(boxed)));
+
+            if let Err(err) = value.add_finalizer(&release) {
+                remove_box(key);
+                return Err(err);
+            }
+
+            Ok(RustBox {
+                value: value.pin()?,
+                _marker: PhantomData,
+            })
+        })
+    }
+
+    /// Checks if `value` is a boxed `T`.
+    pub fn is(value: &Value) -> Result<bool> {
+        let datatype = RustBox::<T>::datatype()?;
+        Ok(value.datatype()?.lock()? == datatype.lock()?)
+    }
+
+    /// Turns a Value back into a handle to the boxed `T`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::TypeMismatch if `value` doesn't box a `T`.
+    pub fn downcast(value: &Value) -> Result<RustBox<T>> {
+        if !RustBox::<T>::is(value)? {
+            return Err(Error::TypeMismatch);
+        }
+
+        Ok(RustBox {
+            value: value.pin()?,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Immutably borrows the boxed value. The value stays alive while the
+    /// borrow does, even if Julia code finalizes the box in the meantime.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::NullPointer if Julia code already finalized the box.
+    pub fn get(&self) -> Result<BoxRef<T>> {
+        let key = self.value.lock()? as usize;
+        let boxed = BOXES.with(|boxes| boxes.borrow().get(&key).cloned());
+        match boxed {
+            Some(boxed) => Ok(BoxRef {
+                boxed: boxed,
+                _marker: PhantomData,
+            }),
+            None => Err(Error::NullPointer),
+        }
+    }
+
+    /// Returns the Julia object holding the boxed value.
+    pub fn value(&self) -> &Value {
+        self.value.get()
+    }
+}
+
+/// A borrow of the value inside a `RustBox`, created by `RustBox::get`.
+pub struct BoxRef<T> {
+    boxed: Rc<Boxed>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Deref for BoxRef<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*(self.boxed.ptr as *const T) }
+    }
+}
@@ -3,8 +3,15 @@
 //! and abstract types describing them.
 //!
 //! Char and all Number subtypes are included, except for Irrational.
+//! `Complex` and `Rational` of any isbits parameter can be boxed into and
+//! unboxed from Julia values.
 
 use std::fmt;
+use std::convert::TryFrom;
+use std::ops::{Add, Sub, Mul, Div, Neg};
+
+use error::{Result, Error};
+use api::{Value, IsBits};
 
 /// Corresponds to the Number abstract type.
 pub trait Number {}
@@ -100,6 +107,7 @@ impl Real for Float64 {}
 impl AbstractFloat for Float64 {}
 
 /// Corresponds to the Complex{T<:Real} generic type.
+#[repr(C)]
 #[derive(Default, Clone, Copy, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Complex<T: Number + Real> {
     pub a: T,
@@ -120,7 +128,83 @@ impl<T: Number + Real + fmt::Display> fmt::Display for Complex<T> {
 
 impl<T: Number + Real> Number for Complex<T> {}
 
+impl<T: Number + Real> Complex<T> {
+    /// Constructs a new Complex from its real and imaginary parts.
+    pub fn new(a: T, b: T) -> Complex<T> {
+        Complex { a: a, b: b }
+    }
+}
+
+impl<T: Number + Real + Add<Output = T>> Add for Complex<T> {
+    type Output = Complex<T>;
+    fn add(self, other: Complex<T>) -> Complex<T> {
+        Complex::new(self.a + other.a, self.b + other.b)
+    }
+}
+
+impl<T: Number + Real + Sub<Output = T>> Sub for Complex<T> {
+    type Output = Complex<T>;
+    fn sub(self, other: Complex<T>) -> Complex<T> {
+        Complex::new(self.a - other.a, self.b - other.b)
+    }
+}
+
+impl<T> Mul for Complex<T>
+where
+    T: Number + Real + Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
+    type Output = Complex<T>;
+    fn mul(self, other: Complex<T>) -> Complex<T> {
+        Complex::new(
+            self.a * other.a - self.b * other.b,
+            self.a * other.b + self.b * other.a,
+        )
+    }
+}
+
+/// Only implemented for floats, since Julia promotes the division of integer
+/// Complexes to `Complex{Float64}`.
+impl<T> Div for Complex<T>
+where
+    T: AbstractFloat + Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>,
+{
+    type Output = Complex<T>;
+    fn div(self, other: Complex<T>) -> Complex<T> {
+        let norm = other.a * other.a + other.b * other.b;
+        Complex::new(
+            (self.a * other.a + self.b * other.b) / norm,
+            (self.b * other.a - self.a * other.b) / norm,
+        )
+    }
+}
+
+impl<T: Number + Real + Neg<Output = T>> Neg for Complex<T> {
+    type Output = Complex<T>;
+    fn neg(self) -> Complex<T> {
+        Complex::new(-self.a, -self.b)
+    }
+}
+
+impl<T: IsBits + Number + Real> TryFrom<Complex<T>> for Value {
+    type Error = Error;
+    fn try_from(c: Complex<T>) -> Result<Value> {
+        Value::from_bits(c)
+    }
+}
+
+impl<'a, T: IsBits + Number + Real> TryFrom<&'a Value> for Complex<T> {
+    type Error = Error;
+    fn try_from(value: &Value) -> Result<Complex<T>> {
+        unbox_bits(value)
+    }
+}
+
 /// Corresponds to the Rational{T<:Integer} generic type.
+///
+/// Like in Julia, the arithmetic operators keep Rationals in lowest terms
+/// with a positive denominator. They panic where Julia would throw an
+/// `OverflowError`; the `checked_*` methods return None instead.
+#[repr(C)]
 #[derive(Default, Clone, Copy, Hash, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Rational<T: Number + Real + Integer> {
     pub num: T,
@@ -141,3 +225,176 @@ impl<T: Number + fmt::Display + Real + Integer> fmt::Display for Rational<T> {
 
 impl<T: Number + Real + Integer> Number for Rational<T> {}
 impl<T: Number + Real + Integer> Real for Rational<T> {}
+
+macro_rules! rational_ops {
+    ($t:ty) => {
+        impl Rational<$t> {
+            /// Returns the greatest common divisor of `a` and `b`, which may
+            /// be negative, or None on overflow.
+            fn checked_gcd(mut a: $t, mut b: $t) -> Option<$t> {
+                while b != 0 {
+                    let r = a.checked_rem(b)?;
+                    a = b;
+                    b = r;
+                }
+                Some(a)
+            }
+
+            /// Divides `a` and `b` by their greatest common divisor.
+            fn divgcd(a: $t, b: $t) -> Option<($t, $t)> {
+                let g = Self::checked_gcd(a, b)?;
+                Some((a.checked_div(g)?, b.checked_div(g)?))
+            }
+
+            /// Adds two Rationals, returning None on overflow.
+            pub fn checked_add(self, other: Rational<$t>) -> Option<Rational<$t>> {
+                let (xd, yd) = Self::divgcd(self.den, other.den)?;
+                let num = self.num.checked_mul(yd)?.checked_add(other.num.checked_mul(xd)?)?;
+                Rational::checked_new(num, self.den.checked_mul(yd)?)
+            }
+
+            /// Subtracts two Rationals, returning None on overflow.
+            pub fn checked_sub(self, other: Rational<$t>) -> Option<Rational<$t>> {
+                let (xd, yd) = Self::divgcd(self.den, other.den)?;
+                let num = self.num.checked_mul(yd)?.checked_sub(other.num.checked_mul(xd)?)?;
+                Rational::checked_new(num, self.den.checked_mul(yd)?)
+            }
+
+            /// Multiplies two Rationals, returning None on overflow.
+            pub fn checked_mul(self, other: Rational<$t>) -> Option<Rational<$t>> {
+                let (xn, yd) = Self::divgcd(self.num, other.den)?;
+                let (xd, yn) = Self::divgcd(self.den, other.num)?;
+                Rational::checked_new(xn.checked_mul(yn)?, xd.checked_mul(yd)?)
+            }
+
+            /// Divides two Rationals, returning None on overflow or if both
+            /// are zero.
+            pub fn checked_div(self, other: Rational<$t>) -> Option<Rational<$t>> {
+                let (xn, yn) = Self::divgcd(self.num, other.num)?;
+                let (xd, yd) = Self::divgcd(self.den, other.den)?;
+                Rational::checked_new(xn.checked_mul(yd)?, xd.checked_mul(yn)?)
+            }
+        }
+
+        impl Add for Rational<$t> {
+            type Output = Rational<$t>;
+            fn add(self, other: Rational<$t>) -> Rational<$t> {
+                self.checked_add(other).expect("overflow in Rational addition")
+            }
+        }
+
+        impl Sub for Rational<$t> {
+            type Output = Rational<$t>;
+            fn sub(self, other: Rational<$t>) -> Rational<$t> {
+                self.checked_sub(other).expect("overflow in Rational subtraction")
+            }
+        }
+
+        impl Mul for Rational<$t> {
+            type Output = Rational<$t>;
+            fn mul(self, other: Rational<$t>) -> Rational<$t> {
+                self.checked_mul(other).expect("overflow in Rational multiplication")
+            }
+        }
+
+        impl Div for Rational<$t> {
+            type Output = Rational<$t>;
+            fn div(self, other: Rational<$t>) -> Rational<$t> {
+                self.checked_div(other).expect("overflow or 0//0 in Rational division")
+            }
+        }
+    };
+}
+
+macro_rules! rational_signed {
+    ($($t:ty),*) => {
+        $(
+            impl Rational<$t> {
+                /// Constructs a new Rational in lowest terms with a positive
+                /// denominator, like Julia's `//`.
+                ///
+                /// ## Panics
+                ///
+                /// Panics if both `num` and `den` are zero or if normalizing
+                /// the sign overflows.
+                pub fn new(num: $t, den: $t) -> Rational<$t> {
+                    if num == 0 && den == 0 {
+                        panic!("invalid rational: 0//0");
+                    }
+                    Rational::checked_new(num, den).expect("overflow in Rational::new")
+                }
+
+                /// Like `new`, but returns None instead of panicking.
+                pub fn checked_new(num: $t, den: $t) -> Option<Rational<$t>> {
+                    let mut g = Self::checked_gcd(num, den)?.checked_abs()?;
+                    if den < 0 {
+                        g = -g;
+                    }
+                    Some(Rational { num: num.checked_div(g)?, den: den.checked_div(g)? })
+                }
+            }
+
+            impl Neg for Rational<$t> {
+                type Output = Rational<$t>;
+                fn neg(self) -> Rational<$t> {
+                    let num = self.num.checked_neg().expect("overflow in Rational negation");
+                    Rational { num: num, den: self.den }
+                }
+            }
+
+            rational_ops!($t);
+        )*
+    };
+}
+
+macro_rules! rational_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Rational<$t> {
+                /// Constructs a new Rational in lowest terms, like Julia's
+                /// `//`.
+                ///
+                /// ## Panics
+                ///
+                /// Panics if both `num` and `den` are zero.
+                pub fn new(num: $t, den: $t) -> Rational<$t> {
+                    Rational::checked_new(num, den).expect("invalid rational: 0//0")
+                }
+
+                /// Like `new`, but returns None instead of panicking.
+                pub fn checked_new(num: $t, den: $t) -> Option<Rational<$t>> {
+                    let g = Self::checked_gcd(num, den)?;
+                    Some(Rational { num: num.checked_div(g)?, den: den.checked_div(g)? })
+                }
+            }
+
+            rational_ops!($t);
+        )*
+    };
+}
+
+rational_signed!(i8, i16, i32, i64, isize);
+rational_unsigned!(u8, u16, u32, u64, usize);
+
+impl<T: IsBits + Number + Real + Integer> TryFrom<Rational<T>> for Value {
+    type Error = Error;
+    fn try_from(r: Rational<T>) -> Result<Value> {
+        Value::from_bits(r)
+    }
+}
+
+impl<'a, T: IsBits + Number + Real + Integer> TryFrom<&'a Value> for Rational<T> {
+    type Error = Error;
+    fn try_from(value: &Value) -> Result<Rational<T>> {
+        unbox_bits(value)
+    }
+}
+
+/// Copies an isbits value out of `value`.
+fn unbox_bits<T: IsBits>(value: &Value) -> Result<T> {
+    match value.bits_ref::<T>() {
+        Ok(bits) => Ok(*bits),
+        Err(Error::TypeMismatch) => Err(Error::InvalidUnbox),
+        Err(err) => Err(err),
+    }
+}
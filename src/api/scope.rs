@@ -0,0 +1,178 @@
+//! Module providing GC-rooted scopes for Julia values.
+//!
+//! Every handle such as `Value` roots its object in a global registry for as
+//! long as the handle or one of its clones is alive. Raw pointers obtained
+//! with `lock` or `into_inner` aren't protected by anything once the handle is
+//! gone, though. A `Scope` pushes a frame onto the Julia GC stack and every
+//! value rooted in it stays alive until the scope is left. The handles it
+//! returns borrow the scope, so the borrow checker makes sure that they can't
+//! be used after their roots are gone.
+
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::ptr;
+use std::ops::Deref;
+
+use sys::*;
+use error::Result;
+use api::JlValue;
+
+/// A GC frame with two direct roots, laid out like the frames built by
+/// `JL_GC_PUSH2`.
+#[repr(C)]
+struct GcFrame {
+    nroots: usize,
+    prev: *mut jl_gcframe_t,
+    root: *mut jl_value_t,
+    /// The value being rooted while the roots vector is allocated or grown.
+    pending: *mut jl_value_t,
+}
+
+/// Runs `f` with `raw` rooted by a GC frame of its own. This is for code
+/// below the level of handles, e.g. the code rooting them.
+///
+/// This function is unsafe, because `f` must neither throw a Julia exception
+/// nor leave frames of its own on the GC stack.
+pub(crate) unsafe fn with_root<T, F>(raw: *mut jl_value_t, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let frame = UnsafeCell::new(GcFrame {
+        nroots: 2 << 1,
+        prev: jl_pgcstack(),
+        root: raw,
+        pending: ptr::null_mut(),
+    });
+    (*jl_get_ptls_states()).pgcstack = frame.get() as *mut jl_gcframe_t;
+
+    let ret = f();
+    JL_GC_POP();
+    ret
+}
+
+/// A GC frame keeping a growing set of Julia values alive.
+///
+/// The frame roots a single `Vector{Any}` and every rooted value is pushed
+/// into it, so a scope can hold any number of values. The vector is only
+/// allocated by the first call to `root`, so entering a scope never runs the
+/// GC. Scopes can only be
+/// entered through `Julia::scope` and `Scope::scope`, which guarantees that
+/// frames are pushed and popped in stack order.
+pub struct Scope {
+    frame: Box<UnsafeCell<GcFrame>>,
+}
+
+impl Scope {
+    /// Push a new frame onto the GC stack.
+    fn new() -> Result<Scope> {
+        let frame = Box::new(UnsafeCell::new(GcFrame {
+            // Direct roots are encoded as `nroots << 1`.
+            nroots: 2 << 1,
+            prev: unsafe { jl_pgcstack() },
+            root: ptr::null_mut(),
+            pending: ptr::null_mut(),
+        }));
+
+        unsafe {
+            (*jl_get_ptls_states()).pgcstack = frame.get() as *mut jl_gcframe_t;
+        }
+
+        Ok(Scope { frame: frame })
+    }
+
+    /// Returns the roots vector, which is null until something is rooted.
+    fn roots(&self) -> *mut jl_array_t {
+        unsafe { (*self.frame.get()).root as *mut jl_array_t }
+    }
+
+    /// Run `f` inside a new scope. All values rooted in the scope are
+    /// released once `f` returns.
+    pub fn enter<T, F>(f: F) -> Result<T>
+    where
+        F: FnOnce(&Scope) -> Result<T>,
+    {
+        let scope = Scope::new()?;
+        f(&scope)
+    }
+
+    /// Run `f` inside a new scope nested in this one.
+    pub fn scope<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Scope) -> Result<T>,
+    {
+        Scope::enter(f)
+    }
+
+    /// Root `value` in this scope. The returned handle can't outlive the
+    /// scope.
+    pub fn root<'s, T, V: JlValue<T>>(&'s self, value: V) -> Result<Rooted<'s, V>> {
+        let raw = value.lock()?;
+        unsafe {
+            let frame = self.frame.get();
+            (*frame).pending = raw as *mut jl_value_t;
+            if (*frame).root.is_null() {
+                (*frame).root = jl_alloc_vec_any(0) as *mut jl_value_t;
+            }
+            if !(*frame).root.is_null() {
+                jl_array_ptr_1d_push(self.roots(), raw as *mut jl_value_t);
+            }
+            (*frame).pending = ptr::null_mut();
+        }
+        jl_catch!();
+
+        Ok(Rooted {
+            value: value,
+            _scope: PhantomData,
+        })
+    }
+
+    /// Returns the number of values rooted in this scope.
+    pub fn len(&self) -> usize {
+        let roots = self.roots();
+        if roots.is_null() {
+            return 0;
+        }
+        unsafe { jl_array_len(roots) }
+    }
+
+    /// Checks if no values are rooted in this scope.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        unsafe {
+            debug_assert!(
+                jl_pgcstack() == self.frame.get() as *mut jl_gcframe_t,
+                "GC frames popped out of order"
+            );
+            JL_GC_POP();
+        }
+    }
+}
+
+/// A handle to a value rooted in a `Scope`.
+///
+/// Note that cloning the inner value, e.g. a `Value`, yields a handle which
+/// is not bound to the scope and only roots the object by itself.
+#[derive(Clone)]
+pub struct Rooted<'s, V> {
+    value: V,
+    _scope: PhantomData<&'s Scope>,
+}
+
+impl<'s, V> Rooted<'s, V> {
+    /// Immutably borrows the rooted value.
+    pub fn get(&self) -> &V {
+        &self.value
+    }
+}
+
+impl<'s, V> Deref for Rooted<'s, V> {
+    type Target = V;
+    fn deref(&self) -> &V {
+        &self.value
+    }
+}
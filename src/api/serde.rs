@@ -0,0 +1,687 @@
+//! Module providing a serde `Serializer` and `Deserializer` for Julia values.
+//!
+//! Only available with the `serde` feature. Scalars are boxed and unboxed
+//! like with `Value::from` and `TryFrom<&Value>`. Sequences become `Vector`s,
+//! tuples `Tuple`s, maps `Dict`s and structs `NamedTuple`s or `Dict`s, see
+//! `StructRepr`. `None` and `()` become `nothing`, unit variants become
+//! symbols and other variants a single-entry struct keyed by the variant
+//! name, like serde's externally tagged enums.
+//!
+//! The `Deserializer` reads any of those back, as well as arbitrary Julia
+//! structs, which are read like maps from field names to values.
+
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+use serde::ser::{self, Serialize};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::de::value::StringDeserializer;
+
+use sys::*;
+use error::{Result, Error};
+use api::{Value, JlValue, Array, Datatype, PinnedValue, Scope};
+use api::collection::{Entries, helper, dict_entries};
+use api::convert::{field_at, symbol, symbol_name};
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Serializes `value` into a new Julia value, representing structs as
+/// `NamedTuple`s.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value> {
+    value.serialize(Serializer::new())
+}
+
+/// Deserializes a `T` from a Julia value.
+pub fn from_value<T: DeserializeOwned>(value: &Value) -> Result<T> {
+    T::deserialize(Deserializer::new(value.clone()))
+}
+
+/// How the `Serializer` represents structs.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum StructRepr {
+    /// As `NamedTuple`s, or as `Dict`s on Julia versions without them.
+    NamedTuple,
+    /// As `Dict`s from the field names as `String`s to the values.
+    Dict,
+}
+
+/// A serde Serializer building Julia values.
+#[derive(Clone, Copy, Debug)]
+pub struct Serializer {
+    structs: StructRepr,
+}
+
+impl Serializer {
+    /// Construct a new Serializer representing structs as `NamedTuple`s.
+    pub fn new() -> Serializer {
+        Serializer { structs: StructRepr::NamedTuple }
+    }
+
+    /// Sets how structs are represented.
+    pub fn structs(mut self, structs: StructRepr) -> Serializer {
+        self.structs = structs;
+        self
+    }
+
+    /// Builds a struct from a `Vector{Any}` of names and one of values.
+    fn new_struct(&self, keys: &Value, values: &Value) -> Result<Value> {
+        match self.structs {
+            StructRepr::NamedTuple => helper("namedtuple")?.call(&[keys.clone(), values.clone()]),
+            StructRepr::Dict => helper("dict")?.call(&[keys.clone(), values.clone()]),
+        }
+    }
+
+    /// Wraps the value of an enum variant in a single-entry struct.
+    fn variant(&self, variant: &str, value: Value) -> Result<Value> {
+        Scope::enter(|scope| {
+            let value = scope.root(value)?;
+            let keys = scope.root(Array::from_values(vec![Value::from(variant)])?)?;
+            let values = scope.root(Array::from_values(vec![value.get().clone()])?)?;
+            self.new_struct(&keys.to_value()?, &values.to_value()?)
+        })
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Serializer {
+        Serializer::new()
+    }
+}
+
+macro_rules! serialize_simple {
+    ($( $method:ident: $t:ty ),*) => {
+        $(
+            fn $method(self, v: $t) -> Result<Value> {
+                Ok(Value::from(v))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = Compound;
+    type SerializeTuple = Compound;
+    type SerializeTupleStruct = Compound;
+    type SerializeTupleVariant = Compound;
+    type SerializeMap = Compound;
+    type SerializeStruct = Compound;
+    type SerializeStructVariant = Compound;
+
+    serialize_simple! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Value::from_value(Array::from_vec(v.to_vec())?)
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::nothing())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::nothing())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::nothing())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        symbol(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        let value = value.serialize(self)?;
+        self.variant(variant, value)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound> {
+        Compound::new(self, Kind::Seq, None)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound> {
+        Compound::new(self, Kind::Tuple, None)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound> {
+        Compound::new(self, Kind::Tuple, None)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound> {
+        Compound::new(self, Kind::Tuple, Some(variant))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound> {
+        Compound::new(self, Kind::Map, None)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound> {
+        Compound::new(self, Kind::Struct, None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound> {
+        Compound::new(self, Kind::Struct, Some(variant))
+    }
+}
+
+/// The kind of value a `Compound` builds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    Seq,
+    Tuple,
+    Map,
+    Struct,
+}
+
+/// Collects the elements of sequences, tuples, maps and structs.
+///
+/// Keys and values are pushed into two pinned `Vector{Any}`s, which are
+/// turned into the final value once serialization ends.
+pub struct Compound {
+    serializer: Serializer,
+    kind: Kind,
+    variant: Option<&'static str>,
+    keys: PinnedValue,
+    values: PinnedValue,
+}
+
+impl Compound {
+    fn new(serializer: Serializer, kind: Kind, variant: Option<&'static str>) -> Result<Compound> {
+        let keys = Array::from_values(vec![])?.to_value()?.pin()?;
+        let values = Array::from_values(vec![])?.to_value()?.pin()?;
+
+        Ok(Compound {
+            serializer: serializer,
+            kind: kind,
+            variant: variant,
+            keys: keys,
+            values: values,
+        })
+    }
+
+    fn push_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        let key = key.serialize(self.serializer)?;
+        Array::new(self.keys.lock()? as *mut jl_array_t)?.push(&key)
+    }
+
+    fn push_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let value = value.serialize(self.serializer)?;
+        Array::new(self.values.lock()? as *mut jl_array_t)?.push(&value)
+    }
+
+    fn end(self) -> Result<Value> {
+        let Compound {
+            serializer,
+            kind,
+            variant,
+            keys,
+            values,
+        } = self;
+
+        Scope::enter(move |scope| {
+            let value = match kind {
+                Kind::Seq => helper("vector")?.call(&[values.get().clone()])?,
+                Kind::Tuple => helper("totuple")?.call(&[values.get().clone()])?,
+                Kind::Map => helper("dict")?.call(&[keys.get().clone(), values.get().clone()])?,
+                Kind::Struct => serializer.new_struct(&keys, &values)?,
+            };
+            let value = scope.root(value)?;
+
+            // Unpinning calls into Julia, the result has to be rooted by
+            // then.
+            drop(keys);
+            drop(values);
+
+            match variant {
+                Some(variant) => serializer.variant(variant, value.get().clone()),
+                None => Ok(value.get().clone()),
+            }
+        })
+    }
+}
+
+impl ser::SerializeSeq for Compound {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.push_key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push_key(key)?;
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.push_key(key)?;
+        self.push_value(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        Compound::end(self)
+    }
+}
+
+/// A serde Deserializer reading Julia values.
+///
+/// The value has to be kept alive by the caller, values created while
+/// deserializing are rooted internally.
+pub struct Deserializer {
+    value: Value,
+}
+
+impl Deserializer {
+    /// Construct a new Deserializer reading `value`.
+    pub fn new(value: Value) -> Deserializer {
+        Deserializer { value: value }
+    }
+
+    /// Returns the keys and values of a `Dict` or a struct as two rooted
+    /// `Vector{Any}`s, or None if the value is neither.
+    fn entries<'s>(&self, scope: &'s Scope) -> Result<Option<Entries<'s>>> {
+        let value = &self.value;
+        if let Some(entries) = dict_entries(scope, value)? {
+            return Ok(Some(entries));
+        }
+        if !bool::try_from(&helper("hasfields")?.call(&[value.clone()])?)? {
+            return Ok(None);
+        }
+
+        let keys = helper("structkeys")?.call(&[value.clone()])?;
+        let keys = scope.root(Array::from_value(keys)?)?;
+        let values = helper("structvalues")?.call(&[value.clone()])?;
+        let values = scope.root(Array::from_value(values)?)?;
+        Ok(Some((keys, values)))
+    }
+}
+
+/// Reads the elements of an Array or the fields of a Tuple.
+struct SeqAccess<'a> {
+    value: &'a Value,
+    element: fn(&Value, usize) -> Result<Value>,
+    idx: usize,
+    len: usize,
+}
+
+fn array_element(value: &Value, idx: usize) -> Result<Value> {
+    Array::new(value.lock()? as *mut jl_array_t)?.element(idx)
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.idx >= self.len {
+            return Ok(None);
+        }
+
+        let element = (self.element)(self.value, self.idx)?;
+        self.idx += 1;
+
+        Scope::enter(|scope| {
+            let element = scope.root(element)?;
+            seed.deserialize(Deserializer::new(element.get().clone()))
+                .map(Some)
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.idx)
+    }
+}
+
+/// Reads the entries of a `Dict` or the fields of a struct.
+struct MapAccess<'a> {
+    keys: &'a Array,
+    values: &'a Array,
+    idx: usize,
+    len: usize,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for MapAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.idx >= self.len {
+            return Ok(None);
+        }
+
+        let key = self.keys.element(self.idx)?;
+        seed.deserialize(Deserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self.values.element(self.idx)?;
+        self.idx += 1;
+        seed.deserialize(Deserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.idx)
+    }
+}
+
+/// Reads an enum variant stored as a single-entry struct.
+struct EnumAccess {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess {
+    type Error = Error;
+    type Variant = Deserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Deserializer)> {
+        let variant: StringDeserializer<Error> = self.variant.into_deserializer();
+        let variant = seed.deserialize(variant)?;
+        Ok((variant, Deserializer::new(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = &self.value;
+
+        if value.is_nothing() {
+            visitor.visit_unit()
+        } else if value.is_bool() {
+            visitor.visit_bool(bool::try_from(value)?)
+        } else if value.is_int8() {
+            visitor.visit_i8(i8::try_from(value)?)
+        } else if value.is_int16() {
+            visitor.visit_i16(i16::try_from(value)?)
+        } else if value.is_int32() {
+            visitor.visit_i32(i32::try_from(value)?)
+        } else if value.is_int64() {
+            visitor.visit_i64(i64::try_from(value)?)
+        } else if value.is_uint8() {
+            visitor.visit_u8(u8::try_from(value)?)
+        } else if value.is_uint16() {
+            visitor.visit_u16(u16::try_from(value)?)
+        } else if value.is_uint32() {
+            visitor.visit_u32(u32::try_from(value)?)
+        } else if value.is_uint64() {
+            visitor.visit_u64(u64::try_from(value)?)
+        } else if value.is_float32() {
+            visitor.visit_f32(f32::try_from(value)?)
+        } else if value.is_float64() {
+            visitor.visit_f64(f64::try_from(value)?)
+        } else if value.isa(&Datatype::char())? {
+            visitor.visit_char(char::try_from(value)?)
+        } else if value.is_string() {
+            visitor.visit_string(String::try_from(value)?)
+        } else if let Some(name) = symbol_name(value)? {
+            visitor.visit_string(name)
+        } else if value.is_array() {
+            let len = Array::new(value.lock()? as *mut jl_array_t)?.len()?;
+            visitor.visit_seq(SeqAccess {
+                value: value,
+                element: array_element,
+                idx: 0,
+                len: len,
+            })
+        } else if value.is_tuple() {
+            let len = unsafe { jl_nfields(value.lock()?) };
+            visitor.visit_seq(SeqAccess {
+                value: value,
+                element: field_at,
+                idx: 0,
+                len: len,
+            })
+        } else {
+            Scope::enter(|scope| match self.entries(scope)? {
+                Some((keys, values)) => {
+                    visitor.visit_map(MapAccess {
+                        keys: keys.get(),
+                        values: values.get(),
+                        idx: 0,
+                        len: keys.len()?,
+                    })
+                }
+                None => {
+                    let msg = format!("can't deserialize a {}", value.typename()?);
+                    Err(de::Error::custom(msg))
+                }
+            })
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.value.is_nothing() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.value.is_array() {
+            let array = Array::new(self.value.lock()? as *mut jl_array_t)?;
+            if let Ok(bytes) = array.typed::<u8>() {
+                return visitor.visit_byte_buf(bytes.to_vec()?);
+            }
+        }
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if let Some(name) = symbol_name(&self.value)? {
+            return visitor.visit_enum(name.into_deserializer());
+        }
+        if self.value.is_string() {
+            return visitor.visit_enum(String::try_from(&self.value)?.into_deserializer());
+        }
+
+        Scope::enter(|scope| {
+            let (keys, values) = match self.entries(scope)? {
+                Some(entries) => entries,
+                None => return Err(de::Error::custom("expected a symbol or a struct")),
+            };
+            if keys.len()? != 1 {
+                return Err(de::Error::custom("expected a single-entry struct"));
+            }
+
+            let key = keys.element(0)?;
+            let variant = match symbol_name(&key)? {
+                Some(name) => name,
+                None => String::try_from(&key)?,
+            };
+
+            visitor.visit_enum(EnumAccess {
+                variant: variant,
+                value: values.element(0)?,
+            })
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string unit unit_struct seq
+        tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -4,11 +4,18 @@
 
 use std::convert::TryFrom;
 use std::ffi::CStr;
+use std::cell::Cell;
+use std::ops::Deref;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+use std::fmt;
 
 use sys::*;
 use error::{Result, Error};
 use string::{IntoCString, TryIntoString};
-use api::{Datatype, Function, IntoSymbol};
+use api::{Datatype, IsBits, Function, IntoSymbol, JlIter};
+use api::scope::with_root;
 
 /// The trait implemented by every Julia type.
 pub trait JlValue<T>
@@ -33,7 +40,8 @@ where
     /// Returns Error::PoisonError if the inner Mutex is poisoned.
     fn lock(&self) -> Result<*mut T>;
 
-    /// Take ownership of the inner jl_value.
+    /// Take ownership of the inner jl_value. The handle doesn't root it
+    /// anymore afterwards.
     ///
     /// ## Errors
     ///
@@ -125,6 +133,12 @@ where
         Self::new(raw)
     }
 
+    /// Returns a new Value handle to the same object, e.g. to pass it as an
+    /// argument to a Function.
+    fn to_value(&self) -> Result<Value> {
+        Value::new(self.lock()? as *mut jl_value_t)
+    }
+
     /// Consumes self and returns an object of another type with the same inner
     /// pointer.
     fn into_value<U, A: JlValue<U>>(self) -> Result<A> {
@@ -138,6 +152,7 @@ macro_rules! simple_jlvalue {
         #[derive(Clone)]
         pub struct $name {
             _inner: ::std::rc::Rc<::std::sync::Mutex<::std::ptr::Unique<$type>>>,
+            _root: ::std::rc::Rc<$crate::api::value::Root>,
         }
 
         impl $crate::api::JlValue<$type> for $name {
@@ -148,6 +163,7 @@ macro_rules! simple_jlvalue {
                                     ::std::ptr::Unique::new_unchecked(_inner)
                                 )
                             ),
+                    _root: ::std::rc::Rc::new($crate::api::value::Root::new(_inner)),
                 }
             }
 
@@ -203,7 +219,7 @@ macro_rules! simple_jlvalue {
                 let value = $crate::api::Value::new(inner as *mut jl_value_t)
                     .map_err(|_| ::std::fmt::Error)?;
 
-                let string = jl_string.call1(&value)
+                let string = jl_string.call(&[value])
                     .map_err(|_| ::std::fmt::Error)?;
                 let string = String::try_from(&string)
                     .map_err(|_| ::std::fmt::Error)?;
@@ -257,6 +273,19 @@ impl Value {
         unsafe { Value::new_unchecked(jl_nothing) }
     }
 
+    /// Returns an iterator over a Julia iterable, e.g. a generator, range or
+    /// the keys of a Dict. Items are produced lazily through Julia's
+    /// iteration protocol.
+    pub fn iter(&self) -> Result<JlIter> {
+        JlIter::new(self.clone())
+    }
+
+    /// Pin the Value, protecting it from the garbage collector until the
+    /// returned handle is dropped.
+    pub fn pin(&self) -> Result<PinnedValue> {
+        PinnedValue::new(self.clone())
+    }
+
     /// Applies function to the inner pointer.
     pub fn map<T, F>(&self, f: F) -> Result<T>
     where
@@ -289,6 +318,30 @@ impl Value {
         !self._inner.is_poisoned()
     }
 
+    /// Boxes an isbits Rust value into a new Julia value of its Datatype.
+    pub fn from_bits<T: IsBits>(bits: T) -> Result<Value> {
+        let datatype = T::datatype()?;
+        let data = &bits as *const T;
+        let raw = unsafe { jl_new_bits(datatype.lock()? as *mut _, data as *mut _) };
+        jl_catch!();
+        Value::new(raw)
+    }
+
+    /// Reinterprets an isbits value as a reference to `T` without copying.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::TypeMismatch if the Value isn't of type `T::datatype()`.
+    pub fn bits_ref<T: IsBits>(&self) -> Result<&T> {
+        let datatype = T::datatype()?;
+        if self.datatype()?.lock()? != datatype.lock()? {
+            return Err(Error::TypeMismatch);
+        }
+
+        let ptr = self.lock()? as *const T;
+        Ok(unsafe { &*ptr })
+    }
+
     /// Checks if the Value is of a concrete Datatype.
     pub fn isa(&self, other: &Datatype) -> Result<bool> {
         let p = unsafe { jl_isa(self.lock()?, other.lock()? as *mut _) != 0 };
@@ -553,6 +606,203 @@ impl Default for Value {
     }
 }
 
+/// Name of the global in `Main` holding the slots of the root registry.
+const ROOTS_NAME: &[u8] = b"__JuliaRsRoots\0";
+
+/// Number of slots the root registry starts with.
+const ROOTS_CAPACITY: usize = 64;
+
+/// Set once `jl_atexit_hook` ran. Julia still reports being initialized
+/// afterwards, but nothing may be unrooted anymore.
+static EXITED: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Records that Julia was shut down, see `EXITED`.
+pub(crate) fn set_exited() {
+    EXITED.store(true, Ordering::SeqCst);
+}
+
+/// Checks if values can currently be rooted and unrooted.
+fn is_running() -> bool {
+    unsafe { jl_is_initialized() != 0 } && !EXITED.load(Ordering::SeqCst)
+}
+
+/// Registry of global roots.
+///
+/// Every root is a slot of a `Vector{Any}` bound in `Main`. Slots are only
+/// ever addressed by their index, so rooting the same object twice takes two
+/// independent slots. There's always at least one free slot, so storing an
+/// object never allocates before it's rooted.
+struct Roots {
+    slots: *mut jl_array_t,
+    /// Indices of the unused slots.
+    free: Vec<usize>,
+}
+
+thread_local! {
+    /// The registry is leaked rather than dropped with the thread, as roots
+    /// kept in other thread locals may still be released during their
+    /// destruction.
+    static ROOTS: Cell<*mut Roots> = Cell::new(ptr::null_mut());
+}
+
+impl Roots {
+    /// Returns the registry of this thread, allocating the slots if needed.
+    ///
+    /// This function is unsafe, because the returned reference must not be
+    /// held across another call to it.
+    unsafe fn get() -> Option<&'static mut Roots> {
+        let roots = ROOTS.with(|roots| {
+            if roots.get().is_null() {
+                let slots = jl_alloc_vec_any(ROOTS_CAPACITY);
+                if slots.is_null() || !jl_exception_occurred().is_null() {
+                    return ptr::null_mut();
+                }
+
+                // Binding the slots may allocate too.
+                with_root(slots as *mut jl_value_t, || {
+                    let name = jl_symbol(ROOTS_NAME.as_ptr() as *const c_char);
+                    jl_set_const(jl_main_module, name, slots as *mut jl_value_t);
+                });
+                if !jl_exception_occurred().is_null() {
+                    return ptr::null_mut();
+                }
+
+                roots.set(Box::into_raw(Box::new(Roots {
+                    slots: slots,
+                    free: (0..ROOTS_CAPACITY).rev().collect(),
+                })));
+            }
+            roots.get()
+        });
+        roots.as_mut()
+    }
+
+    /// Stores `raw` in a free slot and returns its index.
+    unsafe fn insert(&mut self, raw: *mut jl_value_t) -> Option<usize> {
+        let slot = self.free.pop()?;
+        jl_array_ptr_set(self.slots, slot, raw);
+
+        if self.free.is_empty() {
+            // `raw` is already rooted by its slot, the others are empty.
+            let len = jl_array_len(self.slots);
+            jl_array_grow_end(self.slots, len);
+            if jl_exception_occurred().is_null() {
+                self.free.extend((len..2 * len).rev());
+            }
+        }
+        Some(slot)
+    }
+
+    /// Empties the slot `slot`.
+    unsafe fn remove(&mut self, slot: usize) {
+        // Storing null doesn't need a write barrier.
+        *jl_array_ptr_data(self.slots).offset(slot as isize) = ptr::null_mut();
+        self.free.push(slot);
+    }
+}
+
+/// A slot in the root registry, keeping an object alive until it's dropped.
+#[doc(hidden)]
+pub struct Root {
+    slot: Option<usize>,
+}
+
+impl Root {
+    /// Roots `raw` in a new slot. Null pointers aren't rooted, and neither is
+    /// anything before Julia is initialized or after it was shut down. A
+    /// Julia exception raised while allocating slots is left pending.
+    ///
+    /// This function is unsafe, because `raw` must point to a live object.
+    pub unsafe fn new<T>(raw: *mut T) -> Root {
+        let raw = raw as *mut jl_value_t;
+        if raw.is_null() || !is_running() {
+            return Root { slot: None };
+        }
+
+        // Allocating the registry can run the GC before `raw` has a slot.
+        let slot = with_root(raw, || Roots::get().and_then(|roots| roots.insert(raw)));
+        Root { slot: slot }
+    }
+
+    /// Checks if an object was actually rooted.
+    pub fn is_rooted(&self) -> bool {
+        self.slot.is_some()
+    }
+}
+
+impl Drop for Root {
+    fn drop(&mut self) {
+        if !is_running() {
+            return;
+        }
+        if let Some(slot) = self.slot {
+            unsafe {
+                if let Some(roots) = Roots::get() {
+                    roots.remove(slot);
+                }
+            }
+        }
+    }
+}
+
+/// A Value that is protected from the garbage collector for as long as this
+/// handle is alive, independent of any `Scope`.
+///
+/// Every pinned value takes its own slot in a registry of global roots, so
+/// pinning an object twice keeps it alive until both handles are dropped.
+/// Slots held after the `Julia` instance was dropped are simply abandoned.
+pub struct PinnedValue {
+    value: Value,
+    _root: Root,
+}
+
+impl PinnedValue {
+    /// Pin `value`, registering it as a global root.
+    ///
+    /// ## Errors
+    ///
+    /// Returns Error::JuliaNotInitialized if Julia isn't running.
+    pub fn new(value: Value) -> Result<PinnedValue> {
+        if !is_running() {
+            return Err(Error::JuliaNotInitialized);
+        }
+
+        let root = unsafe { Root::new(value.lock()?) };
+        jl_catch!();
+        if !root.is_rooted() {
+            return Err(Error::NullPointer);
+        }
+
+        Ok(PinnedValue {
+            value: value,
+            _root: root,
+        })
+    }
+
+    /// Pin the inner value once more, returning a new independent handle.
+    pub fn try_clone(&self) -> Result<PinnedValue> {
+        PinnedValue::new(self.value.clone())
+    }
+
+    /// Immutably borrows the pinned value.
+    pub fn get(&self) -> &Value {
+        &self.value
+    }
+}
+
+impl Deref for PinnedValue {
+    type Target = Value;
+    fn deref(&self) -> &Value {
+        &self.value
+    }
+}
+
+impl fmt::Debug for PinnedValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Pinned({:?})", self.value)
+    }
+}
+
 macro_rules! box_simple {
     ($t1:ident) => {
         box_simple!($t1 => $t1, |val| { val } );
@@ -6,6 +6,7 @@ use std::result;
 use std::error;
 use std::io;
 use std::char::CharTryFromError;
+use std::str::Utf8Error;
 use std::string::FromUtf8Error;
 use std::ffi::{FromBytesWithNulError, IntoStringError, NulError};
 use std::sync::PoisonError;
@@ -40,6 +41,26 @@ pub enum Error {
     InvalidSymbol,
     /// Attempt to initialize Julia in a thread where it's already initialized.
     JuliaInitialized,
+    /// Attempt to use Julia before it's initialized.
+    JuliaNotInitialized,
+    /// The Julia type of a value doesn't match the expected Rust type.
+    TypeMismatch,
+    /// Attempt to access an index out-of-bounds.
+    OutOfBounds,
+    /// The number of dimensions doesn't match the expected number.
+    DimensionMismatch,
+    /// The fields of a Julia struct don't match the Rust type, holds the
+    /// name of the offending field.
+    FieldMismatch(String),
+    /// The value returned by a Julia function can't be converted into the
+    /// requested Rust type, holds the Julia type of the value and the error
+    /// raised by the conversion.
+    ReturnMismatch(String, Box<Error>),
+    /// A value is nested more deeply than allowed, e.g. because it refers to
+    /// itself.
+    DepthLimit,
+    /// An error with a custom message, e.g. raised by a serde implementation.
+    Custom(String),
     /// Wrapper for ffi::FromBytesWithNulError.
     CStrError(FromBytesWithNulError),
     /// Wrapper for ffi::NulError.
@@ -51,6 +72,8 @@ pub enum Error {
     ResourceInUse,
     /// Wrapper for char::CharTryFromError.
     UTF8Error(CharTryFromError),
+    /// Wrapper for str::Utf8Error.
+    StrUTF8Error(Utf8Error),
     /// Wrapper for string::FromUtf8Error.
     FromUTF8Error(FromUtf8Error),
     /// Wrapper for ffi::IntoStringError.
@@ -66,12 +89,20 @@ impl fmt::Display for Error {
             Error::CStrError(ref err) => write!(f, "CStrError({})", err),
             Error::CStringError(ref err) => write!(f, "CStringError({})", err),
             Error::UTF8Error(ref err) => write!(f, "UTF8Error({})", err),
+            Error::StrUTF8Error(ref err) => write!(f, "StrUTF8Error({})", err),
             Error::FromUTF8Error(ref err) => write!(f, "FromUTF8Error({})", err),
             Error::IntoStringError(ref err) => write!(f, "IntoStringError({})", err),
             Error::IOError(ref err) => write!(f, "IOError({})", err),
+            Error::FieldMismatch(ref field) => write!(f, "FieldMismatch({})", field),
+            Error::ReturnMismatch(ref typename, ref err) => {
+                write!(f, "ReturnMismatch(cannot convert the returned {}: {})", typename, err)
+            }
+            Error::Custom(ref msg) => write!(f, "{}", msg),
             Error::InvalidUnbox | Error::NotAFunction | Error::CallError | Error::EvalError |
             Error::NullPointer | Error::InvalidSymbol | Error::JuliaInitialized |
-            Error::PoisonError | Error::ResourceInUse => fmt::Debug::fmt(self, f),
+            Error::JuliaNotInitialized | Error::TypeMismatch | Error::OutOfBounds | Error::DimensionMismatch |
+            Error::DepthLimit | Error::PoisonError |
+            Error::ResourceInUse => fmt::Debug::fmt(self, f),
         }
     }
 }
@@ -87,11 +118,20 @@ impl error::Error for Error {
             Error::NullPointer => "the supplied raw pointer is a null pointer",
             Error::InvalidSymbol => "the symbol contains invalid characters",
             Error::JuliaInitialized => "Julia was already initialized",
+            Error::JuliaNotInitialized => "Julia is not initialized",
+            Error::TypeMismatch => "the Julia type doesn't match the expected Rust type",
+            Error::OutOfBounds => "attempt to access index out-of-bounds",
+            Error::DimensionMismatch => "the number of dimensions doesn't match",
+            Error::FieldMismatch(_) => "the fields of the Julia struct don't match",
+            Error::ReturnMismatch(..) => "the returned value can't be converted",
+            Error::DepthLimit => "the value is nested too deeply",
+            Error::Custom(ref msg) => msg,
             Error::CStrError(ref err) => err.description(),
             Error::CStringError(ref err) => err.description(),
             Error::PoisonError => "attempt to use a poisoned mutex",
             Error::ResourceInUse => "attempt to take ownership of a resource in use",
             Error::UTF8Error(ref err) => err.description(),
+            Error::StrUTF8Error(ref err) => err.description(),
             Error::FromUTF8Error(ref err) => err.description(),
             Error::IntoStringError(ref err) => err.description(),
             Error::IOError(ref err) => err.description(),
@@ -104,11 +144,15 @@ impl error::Error for Error {
             Error::CStrError(ref err) => Some(err),
             Error::CStringError(ref err) => Some(err),
             Error::UTF8Error(ref err) => Some(err),
+            Error::StrUTF8Error(ref err) => Some(err),
             Error::FromUTF8Error(ref err) => Some(err),
             Error::IntoStringError(ref err) => Some(err),
             Error::IOError(ref err) => Some(err),
+            Error::ReturnMismatch(_, ref err) => Some(err.as_ref()),
             Error::InvalidUnbox | Error::NotAFunction | Error::CallError | Error::EvalError |
             Error::NullPointer | Error::InvalidSymbol | Error::JuliaInitialized |
+            Error::JuliaNotInitialized | Error::TypeMismatch | Error::OutOfBounds | Error::DimensionMismatch |
+            Error::FieldMismatch(_) | Error::DepthLimit | Error::Custom(_) |
             Error::PoisonError | Error::ResourceInUse => None,
         }
     }
@@ -132,6 +176,12 @@ impl From<CharTryFromError> for Error {
     }
 }
 
+impl From<Utf8Error> for Error {
+    fn from(err: Utf8Error) -> Error {
+        Error::StrUTF8Error(err)
+    }
+}
+
 impl From<FromUtf8Error> for Error {
     fn from(err: FromUtf8Error) -> Error {
         Error::FromUTF8Error(err)
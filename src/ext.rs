@@ -1,6 +1,383 @@
+//! Module providing macros for exporting Rust functions to Julia.
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+use std::slice;
+
+use sys::*;
+use error::Result;
+use api::{Value, JlValue, Module, Scope, RustBox};
+use api::datatype::IsBits;
+use api::exception::guard;
+
+/// Name of the private module holding the glue types for exported functions.
+const EXTERN_MODULE: &str = "__JuliaRsExtern";
+
+/// Source of the private module holding the glue types for exported
+/// functions. Arrays are passed to Rust as a `RawSlice`, the `cconvert` keeps
+/// the array itself rooted for the duration of the `ccall`.
+const EXTERN_JL: &str = "module __JuliaRsExtern
+struct RawSlice
+    ptr::Ptr{Void}
+    len::Csize_t
+end
+Base.cconvert(::Type{RawSlice}, x::Array) = x
+Base.unsafe_convert(::Type{RawSlice}, x::Array) = RawSlice(pointer(x), length(x))
+end";
+
+/// An array passed by pointer and length, laid out like
+/// `__JuliaRsExtern.RawSlice`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RawSlice {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+/// Types that can be arguments of functions exported with `extern_jl!`.
+pub trait ExternArg: Sized {
+    /// The type the argument is passed as through `ccall`.
+    type Raw;
+
+    /// Returns the type of the argument in the generated Julia method.
+    fn julia_type() -> String;
+
+    /// Returns the type of the argument in the `ccall` signature.
+    fn ccall_type() -> String {
+        Self::julia_type()
+    }
+
+    /// Converts the argument received from `ccall`.
+    ///
+    /// This function is unsafe, because borrowed arguments such as `&str`
+    /// and slices point into memory owned by Julia, which is only guaranteed
+    /// to stay alive and unaliased for the duration of the call. The caller
+    /// has to make sure that the returned value doesn't outlive it, e.g. by
+    /// passing it to a function whose signature leaves the lifetime generic.
+    unsafe fn from_raw(raw: Self::Raw) -> Result<Self>;
+}
+
+/// Types that can be returned from functions exported with `extern_jl!`.
+pub trait ExternRet {
+    /// The type the value is returned as through `ccall`.
+    type Raw;
+
+    /// Returns the return type in the `ccall` signature.
+    fn ccall_type() -> String;
+
+    /// Converts the value returned to `ccall`.
+    fn into_raw(self) -> Result<Self::Raw>;
+}
+
+macro_rules! extern_primitive {
+    ( $( $t:ty => $jl:expr ),* ) => {
+        $(
+            impl ExternArg for $t {
+                type Raw = $t;
+
+                fn julia_type() -> String {
+                    String::from($jl)
+                }
+
+                unsafe fn from_raw(raw: $t) -> Result<$t> {
+                    Ok(raw)
+                }
+            }
+
+            impl ExternRet for $t {
+                type Raw = $t;
+
+                fn ccall_type() -> String {
+                    String::from($jl)
+                }
+
+                fn into_raw(self) -> Result<$t> {
+                    Ok(self)
+                }
+            }
+        )*
+    }
+}
+
+extern_primitive! {
+    bool => "Bool",
+    i8 => "Int8",
+    u8 => "UInt8",
+    i16 => "Int16",
+    u16 => "UInt16",
+    i32 => "Int32",
+    u32 => "UInt32",
+    i64 => "Int64",
+    u64 => "UInt64",
+    isize => "Int",
+    usize => "UInt",
+    f32 => "Float32",
+    f64 => "Float64"
+}
+
+impl ExternRet for () {
+    type Raw = ();
+
+    fn ccall_type() -> String {
+        String::from("Void")
+    }
+
+    fn into_raw(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ExternArg for Value {
+    type Raw = *mut jl_value_t;
+
+    fn julia_type() -> String {
+        String::from("Any")
+    }
+
+    unsafe fn from_raw(raw: *mut jl_value_t) -> Result<Value> {
+        Value::new(raw)
+    }
+}
+
+impl ExternRet for Value {
+    type Raw = *mut jl_value_t;
+
+    fn ccall_type() -> String {
+        String::from("Any")
+    }
+
+    fn into_raw(self) -> Result<*mut jl_value_t> {
+        self.lock()
+    }
+}
+
+impl<T: ExternRet> ExternRet for Result<T> {
+    type Raw = T::Raw;
+
+    fn ccall_type() -> String {
+        T::ccall_type()
+    }
+
+    fn into_raw(self) -> Result<T::Raw> {
+        self?.into_raw()
+    }
+}
+
+macro_rules! extern_tuple {
+    ( $( ( $( $t:ident ),+ ) )* ) => {
+        $(
+            /// Multiple values are returned as a Julia `Tuple`.
+            impl<$( $t: Into<Value> ),+> ExternRet for ( $( $t, )+ ) {
+                type Raw = *mut jl_value_t;
+
+                fn ccall_type() -> String {
+                    String::from("Any")
+                }
+
+                #[allow(non_snake_case)]
+                fn into_raw(self) -> Result<*mut jl_value_t> {
+                    let ( $( $t, )+ ) = self;
+                    let tuple = unsafe { Module::new_unchecked(jl_base_module) }.function("tuple")?;
+
+                    Scope::enter(|scope| {
+                        let items = vec![
+                            $( scope.root(Into::<Value>::into($t))?.get().clone() ),+
+                        ];
+                        let tuple = scope.root(tuple.call(&items)?)?;
+                        tuple.lock()
+                    })
+                }
+            }
+        )*
+    }
+}
+
+extern_tuple! {
+    (A, B)
+    (A, B, C)
+    (A, B, C, D)
+    (A, B, C, D, E)
+    (A, B, C, D, E, F)
+}
+
+/// Boxed Rust values are passed as `Any` and checked when converted.
+impl<T: 'static> ExternArg for RustBox<T> {
+    type Raw = *mut jl_value_t;
+
+    fn julia_type() -> String {
+        String::from("Any")
+    }
+
+    unsafe fn from_raw(raw: *mut jl_value_t) -> Result<RustBox<T>> {
+        RustBox::downcast(&Value::new(raw)?)
+    }
+}
+
+impl<T: 'static> ExternRet for RustBox<T> {
+    type Raw = *mut jl_value_t;
+
+    fn ccall_type() -> String {
+        String::from("Any")
+    }
+
+    fn into_raw(self) -> Result<*mut jl_value_t> {
+        // Unpinning can trigger a collection, the scope keeps the value alive
+        // until it's returned.
+        Scope::enter(|scope| {
+            let value = scope.root(self.value().clone())?;
+            drop(self);
+            value.lock()
+        })
+    }
+}
+
+/// The string is only borrowed for the duration of the call, see
+/// `ExternArg::from_raw`.
+impl<'a> ExternArg for &'a str {
+    type Raw = *const c_char;
+
+    fn julia_type() -> String {
+        String::from("String")
+    }
+
+    fn ccall_type() -> String {
+        String::from("Cstring")
+    }
+
+    unsafe fn from_raw(raw: *const c_char) -> Result<&'a str> {
+        Ok(CStr::from_ptr(raw).to_str()?)
+    }
+}
+
+impl ExternArg for String {
+    type Raw = *const c_char;
+
+    fn julia_type() -> String {
+        String::from("String")
+    }
+
+    fn ccall_type() -> String {
+        String::from("Cstring")
+    }
+
+    unsafe fn from_raw(raw: *const c_char) -> Result<String> {
+        <&str>::from_raw(raw).map(String::from)
+    }
+}
+
+/// Strings are returned as a new Julia `String`.
+impl ExternRet for String {
+    type Raw = *mut jl_value_t;
+
+    fn ccall_type() -> String {
+        String::from("Any")
+    }
+
+    fn into_raw(self) -> Result<*mut jl_value_t> {
+        Value::from(self).lock()
+    }
+}
+
+/// Passed as `Ref{T}`, e.g. to write a result into a `Ref{Float64}(0)`.
+impl<'a, T: IsBits + ExternArg> ExternArg for &'a mut T {
+    type Raw = *mut T;
+
+    fn julia_type() -> String {
+        format!("Ref{{{}}}", T::julia_type())
+    }
+
+    unsafe fn from_raw(raw: *mut T) -> Result<&'a mut T> {
+        Ok(&mut *raw)
+    }
+}
+
+/// Arrays of any shape are passed by pointer and length.
+impl<'a, T: IsBits + ExternArg> ExternArg for &'a [T] {
+    type Raw = RawSlice;
+
+    fn julia_type() -> String {
+        format!("Array{{{}}}", T::julia_type())
+    }
+
+    fn ccall_type() -> String {
+        format!("{}.RawSlice", EXTERN_MODULE)
+    }
+
+    unsafe fn from_raw(raw: RawSlice) -> Result<&'a [T]> {
+        Ok(slice::from_raw_parts(raw.ptr as *const T, raw.len))
+    }
+}
+
+/// Arrays of any shape are passed by pointer and length. Passing the same
+/// array twice as a mutable slice is undefined behaviour.
+impl<'a, T: IsBits + ExternArg> ExternArg for &'a mut [T] {
+    type Raw = RawSlice;
+
+    fn julia_type() -> String {
+        format!("Array{{{}}}", T::julia_type())
+    }
+
+    fn ccall_type() -> String {
+        format!("{}.RawSlice", EXTERN_MODULE)
+    }
+
+    unsafe fn from_raw(raw: RawSlice) -> Result<&'a mut [T]> {
+        Ok(slice::from_raw_parts_mut(raw.ptr as *mut T, raw.len))
+    }
+}
+
+/// Returns a `ccall` target calling the function at `ptr` directly.
+#[doc(hidden)]
+pub fn ccall_target(ptr: *const c_void) -> String {
+    format!("Ptr{{Void}}({:#x})", ptr as usize)
+}
+
+/// Builds a Julia method `name` forwarding its arguments to `target` through
+/// `ccall`. `args` holds the name, the Julia type and the `ccall` type of each
+/// argument.
+#[doc(hidden)]
+pub fn ccall_decl(name: &str, target: &str, args: &[(&str, String, String)], ret: &str) -> String {
+    let mut decl = format!("function {}(", name);
+    for &(arg, ref julia_type, _) in args {
+        decl.push_str(&format!("{}::{}, ", arg, julia_type));
+    }
+    decl.push_str(&format!(")\n    ccall({}, {}, (", target, ret));
+    for &(_, _, ref ccall_type) in args {
+        decl.push_str(&format!("{}, ", ccall_type));
+    }
+    decl.push_str("), ");
+    for &(arg, _, _) in args {
+        decl.push_str(&format!("{}, ", arg));
+    }
+    decl.push_str(")\nend\n");
+    decl
+}
+
+/// Loads the glue types used by the declarations of exported functions.
+#[doc(hidden)]
+pub fn load_extern_module() -> Result<()> {
+    Module::main_submodule(EXTERN_MODULE, EXTERN_JL).map(|_| ())
+}
+
+/// Runs the body of a function exported with `extern_jl!`. Unwinding into
+/// libjulia is undefined behaviour, so panics are thrown as an ErrorException
+/// carrying the panic message and errors are rethrown as Julia exceptions.
+#[doc(hidden)]
+pub fn catch_extern<T, F>(f: F) -> T
+where
+    F: FnOnce() -> Result<T>,
+{
+    guard(f)
+}
 
 /// Constructs a Julia function declaration in the form of a stringified Julia expression.
 ///
+/// The Julia types of the arguments and of the return value are taken from
+/// their `ExternArg` and `ExternRet` implementations. Since those are only
+/// known at runtime, the declaration is a `String` rather than a
+/// `&'static str`, and so are the fields of the structs generated by
+/// `extern_jl!`.
+///
 /// # Syntax
 /// decl_jl! {
 ///     pub extern "Julia" fn <libname> :: <func> ( <arg: Type> * ) -> OutType;
@@ -8,11 +385,11 @@
 ///
 /// # Example
 /// ```
-/// let square = decl_jl! {
-///     pub extern "Julia" fn libsquare :: square(x: Float64) -> Float64;
+/// let norm = decl_jl! {
+///     pub extern "Julia" fn libnorm :: norm(xs: &[Float64]) -> Float64;
 /// };
-/// "function square(x::Float64, )
-///     ccall((:square, \"libsquare\"), Float64, (Float64,), x)
+/// "function norm(xs::Array{Float64}, )
+///     ccall((:norm, \"libnorm\"), Float64, (__JuliaRsExtern.RawSlice, ), xs, )
 /// end"
 /// ```
 #[macro_export]
@@ -20,12 +397,20 @@ macro_rules! decl_jl {
     {
         pub extern "Julia" fn $lib:ident :: $name:ident ( $( $arg:ident : $type:ty ),* ) -> $out:ty;
     } => {
-        concat!(
-                "function ", stringify!($name), "(", $( stringify!($arg), "::", stringify!($type), ", ", )* ")\n",
-                    "    ccall((:", stringify!($name), ", \"", stringify!($lib), "\"), ", stringify!($out),
-                    ", (", $( stringify!($type), ",", )* "), ",  $( stringify!($arg), ",", )* ")\n",
-                "end"
-            )
+        $crate::ext::ccall_decl(
+            stringify!($name),
+            concat!("(:", stringify!($name), ", \"", stringify!($lib), "\")"),
+            &[
+                $(
+                    (
+                        stringify!($arg),
+                        <$type as $crate::ext::ExternArg>::julia_type(),
+                        <$type as $crate::ext::ExternArg>::ccall_type(),
+                    ),
+                )*
+            ],
+            &<$out as $crate::ext::ExternRet>::ccall_type(),
+        )
     };
     {
         $(
@@ -34,7 +419,7 @@ macro_rules! decl_jl {
     } => {
         vec![
             $(
-                decl_jl! { pub extern "Julia" fn $lib:ident :: $name:ident ( $( $arg:ident : $type:ty ),* ) -> $out:ty; }
+                decl_jl! { pub extern "Julia" fn $lib :: $name ( $( $arg : $type ),* ) -> $out; }
             ),*
         ]
     }
@@ -42,6 +427,20 @@ macro_rules! decl_jl {
 
 /// Turns Rust functions into Julia extern functions.
 ///
+/// Arguments can be of any type implementing `ExternArg`, e.g. numbers,
+/// `bool`, `&str`, `Value`, slices and `&mut` references to numbers. The
+/// return type must implement `ExternRet`, which includes `Result`s of such
+/// types. Errors are rethrown in Julia, a panic is turned into an
+/// ErrorException.
+///
+/// Borrowed arguments, e.g. `&str` and slices, are only valid for the
+/// duration of the call. Don't declare them with a `'static` lifetime.
+///
+/// Each body becomes an inner function with the declared signature, which
+/// the generated `extern "C"` function calls with the converted arguments.
+/// `return` works as usual, but since the body is a separate function it
+/// can't refer to the raw arguments.
+///
 /// # Syntax
 /// ```
 /// extern_jl! {
@@ -59,6 +458,13 @@ macro_rules! decl_jl {
 ///         pub fn square(x: Float64) -> Float64 {
 ///             x * x
 ///         }
+///
+///         pub fn checked_sqrt(x: Float64) -> Result<Float64> {
+///             if x < 0.0 {
+///                 panic!("negative argument");
+///             }
+///             Ok(x.sqrt())
+///         }
 ///     }
 /// }
 ///
@@ -69,6 +475,7 @@ macro_rules! decl_jl {
 ///     sqr.decl(&mut jl);
 ///
 ///     jl.eval_string("assert(square(5.0) == 25.0)");
+///     jl.eval_string("checked_sqrt(-1.0)").unwrap_err();
 /// }
 /// ```
 #[macro_export]
@@ -82,12 +489,25 @@ macro_rules! extern_jl {
     } => {
         $(
             #[no_mangle]
-            pub extern "C" fn $name ( $( $arg : $type ),* ) -> $out $body
+            pub extern "C" fn $name (
+                $( $arg : <$type as $crate::ext::ExternArg>::Raw ),*
+            ) -> <$out as $crate::ext::ExternRet>::Raw {
+                fn body( $( $arg : $type ),* ) -> $out $body
+
+                $crate::ext::catch_extern(|| {
+                    // The lifetimes of borrowed arguments are generic in
+                    // the signature of `body`, so they can't escape the call.
+                    $(
+                        let $arg = unsafe { <$type as $crate::ext::ExternArg>::from_raw($arg)? };
+                    )*
+                    $crate::ext::ExternRet::into_raw(body( $( $arg ),* ))
+                })
+            }
         )*
 
         struct $struct {
             $(
-                pub $name: &'static str,
+                pub $name: String,
             )*
         }
 
@@ -103,10 +523,11 @@ macro_rules! extern_jl {
             }
 
             pub fn decl(self, jl: &mut $crate::api::Julia) -> $crate::error::Result<$crate::api::Value> {
+                $crate::ext::load_extern_module()?;
                 let mut decl = String::new();
 
                 $(
-                    decl.push_str(self.$name);
+                    decl.push_str(&self.$name);
                 )*
                 jl.load(&mut decl.as_bytes(), Some(concat!( "jl-decl-", stringify!($lib), ".jl" )))
             }
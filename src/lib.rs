@@ -16,7 +16,7 @@
 //!
 //! ```
 //! fn main() {
-//!     use julia::api::{Julia, Value};
+//!     use julia::api::Julia;
 //!
 //!     let mut jl = Julia::new().unwrap();
 //!     jl.eval_string("println(\"Hello, Julia!\")").unwrap();
@@ -24,10 +24,7 @@
 //!
 //!     let sqrt = jl.base().function("sqrt").unwrap();
 //!
-//!     let boxed_x = Value::from(1337.0);
-//!     let boxed_sqrt_x = sqrt.call1(&boxed_x).unwrap();
-//!
-//!     let sqrt_x = f64::try_from(boxed_sqrt_x).unwrap();
+//!     let sqrt_x: f64 = sqrt.invoke((1337.0,)).unwrap();
 //!     println!("{}", sqrt_x);
 //!     // 36.565010597564445
 //! }
@@ -45,6 +42,9 @@
 extern crate libc;
 extern crate smallvec;
 extern crate julia_sys;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
 
 pub mod sys;
 #[macro_use]